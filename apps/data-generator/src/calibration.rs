@@ -0,0 +1,79 @@
+use anyhow::Result;
+use arrow::record_batch::RecordBatch;
+use log::info;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::data_generator::*;
+use crate::generate_parquet_data;
+use crate::job_size::{JobSize, DEFAULT_THREADS};
+
+const CALIBRATION_PROCESS_DATE: &str = "2024-01-15";
+const CALIBRATION_CHARGEBACK_RATE: f64 = 0.001;
+
+/// Measured Parquet size per row for each table, read by the job submitter to size runs
+#[derive(Debug, Serialize)]
+pub struct Calibration {
+    pub sample_rows: usize,
+    pub compression: String,
+    pub created_at: String,
+    pub bytes_per_row: BTreeMap<String, f64>,
+    /// Single-thread time to generate and encode a row, which the job submitter prices runs with
+    pub seconds_per_row: BTreeMap<String, f64>,
+}
+
+/// Generate `sample_rows` rows per table with the real schemas and writer settings, without
+/// touching AWS, and measure the encoded size of each table
+pub async fn run_calibration(sample_rows: usize) -> Result<Calibration> {
+    info!("📏 Calibrating with {} sample rows per table", sample_rows);
+
+    // Stand-in for the DynamoDB pool: hash_pans are 64 hex characters either way
+    let mut rng = StdRng::seed_from_u64(0);
+    let hash_pan_pool: Vec<String> = (0..100)
+        .map(|_| (0..32).map(|_| format!("{:02x}", rng.gen::<u8>())).collect())
+        .collect();
+    let row_seeds: Vec<u64> = (0..sample_rows as u64).collect();
+    let chargeback_row_seeds: Vec<u64> = row_seeds
+        .choose_multiple(&mut rng, (sample_rows as f64 * CALIBRATION_CHARGEBACK_RATE) as usize)
+        .cloned()
+        .collect();
+
+    let (thread_id, partition_job_order) = (1, 1);
+    let job_size = JobSize { threads: DEFAULT_THREADS, rows_per_thread: sample_rows };
+    let timed = |generate: &dyn Fn() -> RecordBatch| {
+        let started = Instant::now();
+        let batch = generate();
+        (batch, started.elapsed())
+    };
+    // Chargebacks are measured on a full-size sample too; a run's real share is too small to size reliably
+    let batches: Vec<(&str, (RecordBatch, Duration))> = vec![
+        ("authorization", timed(&|| generate_authorization_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &chargeback_row_seeds, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+        ("authorization_hash", timed(&|| generate_authorization_hash_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &chargeback_row_seeds, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+        ("clearing", timed(&|| generate_clearing_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &chargeback_row_seeds, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+        ("clearing_hash", timed(&|| generate_clearing_hash_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &chargeback_row_seeds, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+        ("chargeback", timed(&|| generate_chargeback_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+        ("chargeback_hash", timed(&|| generate_chargeback_hash_batch(sample_rows, &row_seeds, partition_job_order, thread_id, job_size, &hash_pan_pool, CALIBRATION_PROCESS_DATE))),
+    ];
+
+    let mut bytes_per_row = BTreeMap::new();
+    let mut seconds_per_row = BTreeMap::new();
+    for (table_name, (batch, generation_time)) in batches {
+        let rows = batch.num_rows().max(1);
+        let started = Instant::now();
+        let bytes = generate_parquet_data(batch)?.len();
+        let seconds = (generation_time + started.elapsed()).as_secs_f64();
+        info!("📏 {}: {} bytes for {} rows ({:.1} bytes/row) in {:.2}s", table_name, bytes, rows, bytes as f64 / rows as f64, seconds);
+        bytes_per_row.insert(table_name.to_string(), bytes as f64 / rows as f64);
+        seconds_per_row.insert(table_name.to_string(), seconds / rows as f64);
+    }
+
+    Ok(Calibration {
+        sample_rows,
+        compression: "SNAPPY".to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        bytes_per_row,
+        seconds_per_row,
+    })
+}
@@ -1,7 +1,8 @@
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
-use std::env;
+
+use crate::job_size::JobSize;
 
 #[derive(Debug, Clone)]
 pub struct TransactionBusinessLogic {
@@ -39,6 +40,7 @@ pub struct TransactionBusinessLogic {
     pub processing_rate: f64,
 }
 
+// `data-validator profile` reports generated data against these rates; update its targets with them
 pub fn generate_transaction_business_logic(
     seeded_rng: &mut rand::rngs::StdRng,
 ) -> TransactionBusinessLogic {
@@ -418,19 +420,15 @@ pub fn generate_generic_data_with_rng<R: Rng>(field_name: &str, rng: &mut R) ->
     }
 }
 
+/// Each partition order owns a block of threads × rows sequence numbers, split between its threads
 pub fn generate_partition_sequence_number(
     row_index: i64,
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
 ) -> String {
-    let num_rows = env::var("NUM_OF_ROWS")
-        .unwrap_or("250000".to_string())
-        .parse::<i64>()
-        .unwrap_or(250000);
-
-    let job_base = partition_job_order * (num_rows * num_threads as i64);
-    let thread_offset = (thread_id as i64 - 1) * num_rows;
+    let job_base = partition_job_order * job_size.rows_per_job();
+    let thread_offset = (thread_id as i64 - 1) * job_size.rows_per_thread as i64;
     let sequence_number = 1000000000000001 + job_base + thread_offset + row_index;
 
     sequence_number.to_string()
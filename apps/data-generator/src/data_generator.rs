@@ -15,6 +15,7 @@ use crate::common::{
     generate_transaction_business_logic,
     generate_alphanumeric_string_with_rng
 };
+use crate::job_size::JobSize;
 
 // Generates data for each row by matching whether the field containes in the table
 fn generate_data_for_given_field(
@@ -23,7 +24,7 @@ fn generate_data_for_given_field(
     row_index: i64,
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     chargeback_row_seeds: &[u64], 
     is_chargeback_table: bool,
     process_date: &str, 
@@ -79,10 +80,10 @@ fn generate_data_for_given_field(
             if is_chargeback_table {
                 // Extract original row_index from row_seed to match 
                 // authorization and clearing sequence numbers for the given row
-                let row_index = (row_seed % 100000) as i64;
-                generate_partition_sequence_number(row_index, partition_job_order, thread_id, num_threads)
+                let row_index = job_size.row_index_of_seed(row_seed);
+                generate_partition_sequence_number(row_index, partition_job_order, thread_id, job_size)
             } else {
-                generate_partition_sequence_number(row_index, partition_job_order, thread_id, num_threads)
+                generate_partition_sequence_number(row_index, partition_job_order, thread_id, job_size)
             }
         },
         "transaction_amount" | "settlement_amount" | "transaction_fee_amount" | "transaction_amount_cents" |
@@ -1176,7 +1177,7 @@ fn generate_batch_with_seeds(
     row_seeds: &[u64], 
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str, 
@@ -1241,7 +1242,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1261,7 +1262,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1282,7 +1283,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1303,7 +1304,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1324,7 +1325,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1344,7 +1345,7 @@ fn generate_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1376,13 +1377,15 @@ fn generate_batch_with_seeds(
                                         row_index as i64,
                                         partition_job_order,
                                         thread_id,     
-                                        num_threads,
+                                        job_size,
                                         chargeback_row_seeds, 
                                         is_chargeback_table, 
                                         process_date, 
                                         hash_pan_pool
                                     );
-                                    field_value.parse::<f64>().unwrap_or_else(|_| rng.gen_range(1.0..1000000.0)) as i128 * 10_i128.pow(scale as u32)
+                                    // Scale before converting, so the fraction survives (12.34 → 1234 at scale 2)
+                                    let value = field_value.parse::<f64>().unwrap_or_else(|_| rng.gen_range(1.0..1000000.0));
+                                    (value * 10_f64.powi(scale as i32)).round() as i128
                                 }).collect();
                                 arrays.push(Arc::new(Decimal128Array::from(values).with_precision_and_scale(precision, scale).unwrap()));
                             }
@@ -1402,7 +1405,7 @@ fn generate_hash_batch_with_seeds(
     row_seeds: &[u64], 
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str, 
@@ -1439,7 +1442,7 @@ fn generate_hash_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1459,7 +1462,7 @@ fn generate_hash_batch_with_seeds(
                 row_index as i64, 
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1480,7 +1483,7 @@ fn generate_hash_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1500,7 +1503,7 @@ fn generate_hash_batch_with_seeds(
                 row_index as i64,
                 partition_job_order,
                 thread_id,     
-                num_threads,
+                job_size,
                 chargeback_row_seeds, 
                 is_chargeback_table, 
                 process_date, 
@@ -1519,7 +1522,7 @@ pub fn generate_authorization_batch(
     row_seeds: &[u64], 
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str
@@ -1531,7 +1534,7 @@ pub fn generate_authorization_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         chargeback_row_seeds, 
         hash_pan_pool, 
         process_date, 
@@ -1544,7 +1547,7 @@ pub fn generate_clearing_batch(
     row_seeds: &[u64], 
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str
@@ -1556,7 +1559,7 @@ pub fn generate_clearing_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         chargeback_row_seeds, 
         hash_pan_pool, 
         process_date, 
@@ -1569,7 +1572,7 @@ pub fn generate_chargeback_batch(
     row_seeds: &[u64], 
     partition_job_order: i64,
     thread_id: i32,
-    num_threads: i32,
+    job_size: JobSize,
     hash_pan_pool: &[String], 
     process_date: &str
 ) -> RecordBatch {
@@ -1580,7 +1583,7 @@ pub fn generate_chargeback_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         &[], 
         hash_pan_pool, 
         process_date, 
@@ -1593,7 +1596,7 @@ pub fn generate_authorization_hash_batch(
     row_seeds: &[u64], 
     partition_job_order: i64, 
     thread_id: i32, 
-    num_threads: i32, 
+    job_size: JobSize, 
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str
@@ -1605,7 +1608,7 @@ pub fn generate_authorization_hash_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         chargeback_row_seeds, 
         hash_pan_pool, 
         process_date, 
@@ -1618,7 +1621,7 @@ pub fn generate_clearing_hash_batch(
     row_seeds: &[u64], 
     partition_job_order: i64, 
     thread_id: i32, 
-    num_threads: i32, 
+    job_size: JobSize, 
     chargeback_row_seeds: &[u64], 
     hash_pan_pool: &[String], 
     process_date: &str
@@ -1630,7 +1633,7 @@ pub fn generate_clearing_hash_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         chargeback_row_seeds, 
         hash_pan_pool, 
         process_date, 
@@ -1643,7 +1646,7 @@ pub fn generate_chargeback_hash_batch(
     row_seeds: &[u64], 
     partition_job_order: i64, 
     thread_id: i32, 
-    num_threads: i32, 
+    job_size: JobSize, 
     hash_pan_pool: &[String], 
     process_date: &str
 ) -> RecordBatch {
@@ -1654,7 +1657,7 @@ pub fn generate_chargeback_hash_batch(
         row_seeds, 
         partition_job_order, 
         thread_id, 
-        num_threads, 
+        job_size, 
         &[], 
         hash_pan_pool, 
         process_date, 
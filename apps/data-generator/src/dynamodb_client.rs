@@ -3,9 +3,26 @@ use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
 use rand::Rng;
 use anyhow::Result;
 use log::{info, warn, error, debug};
+use std::collections::HashMap;
 
-pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &DynamoDbClient, table_name: &str, job_id: &str) -> Result<i64> {
+use crate::metrics::{count_dynamodb_call, count_dynamodb_error};
+
+// Reserved id of the hash PAN table item written by dynamodb-seeder with table-wide settings
+const METADATA_ITEM_ID: i64 = -1;
+// Card count assumed for tables seeded before the count was recorded in the metadata item
+const DEFAULT_CARD_COUNT: u64 = 100000;
+
+/// Table-wide settings recorded by dynamodb-seeder in the hash PAN table metadata item
+#[derive(Debug, Clone)]
+pub struct HashPanTableMetadata {
+    /// None for tables seeded before keyed hashing
+    pub hash_key_id: Option<String>,
+    pub card_count: u64,
+}
+
+pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &DynamoDbClient, table_name: &str, job_id: &str, run_id: Option<&str>) -> Result<i64> {
     // 1. Check if job already exists in active_jobs
+    count_dynamodb_call();
     let get_response = dynamodb_client
         .get_item()
         .table_name(table_name)
@@ -28,16 +45,22 @@ pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &Dynam
         }
     }
     
-    // 2. Job not found, increment counter
-    let update_response = dynamodb_client
+    // 2. Job not found, increment counter, and the run's own count so a purge knows whether the
+    //    date was used by other runs too
+    let mut counter_update = dynamodb_client
         .update_item()
         .table_name(table_name)
         .key("partition_date", AttributeValue::S(process_date.to_string()))
         .update_expression("ADD job_counter :inc")
         .expression_attribute_values(":inc", AttributeValue::N("1".to_string()))
-        .return_values(ReturnValue::UpdatedNew)
-        .send()
-        .await?;
+        .return_values(ReturnValue::UpdatedNew);
+    if let Some(run_id) = run_id {
+        counter_update = counter_update
+            .update_expression("ADD job_counter :inc, #run_jobs :inc")
+            .expression_attribute_names("#run_jobs", format!("run_jobs_{}", run_id));
+    }
+    count_dynamodb_call();
+    let update_response = counter_update.send().await?;
         
     let job_counter = update_response
         .attributes()
@@ -49,6 +72,7 @@ pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &Dynam
     let partition_order = job_counter - 1;
     
     // 3. Assign job to partition (handle active_jobs creation)
+    count_dynamodb_call();
     let assign_result = dynamodb_client
         .update_item()
         .table_name(table_name)
@@ -61,6 +85,8 @@ pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &Dynam
         
     // If active_jobs doesn't exist, create it first then retry
     if assign_result.is_err() {
+        count_dynamodb_error();
+        count_dynamodb_call();
         dynamodb_client
             .update_item()
             .table_name(table_name)
@@ -70,6 +96,7 @@ pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &Dynam
             .send()
             .await?;
             
+        count_dynamodb_call();
         dynamodb_client
             .update_item()
             .table_name(table_name)
@@ -86,6 +113,7 @@ pub async fn get_partition_job_order(process_date: &str, dynamodb_client: &Dynam
 }
 
 pub async fn mark_job_completed(process_date: &str, dynamodb_client: &DynamoDbClient, table_name: &str, job_id: &str) -> Result<()> {
+    count_dynamodb_call();
     dynamodb_client
         .update_item()
         .table_name(table_name)
@@ -99,13 +127,55 @@ pub async fn mark_job_completed(process_date: &str, dynamodb_client: &DynamoDbCl
     Ok(())
 }
 
+pub async fn get_hash_pan_table_metadata(dynamodb_client: &DynamoDbClient, table_name: &str) -> Result<HashPanTableMetadata> {
+    count_dynamodb_call();
+    let response = dynamodb_client
+        .get_item()
+        .table_name(table_name)
+        .key("id", AttributeValue::N(METADATA_ITEM_ID.to_string()))
+        .send()
+        .await?;
+
+    let item = response.item.unwrap_or_default();
+    let hash_key_id = item.get("hash_key_id").and_then(|v| v.as_s().ok()).cloned();
+    let card_count = item
+        .get("card_count")
+        .and_then(|v| v.as_n().ok())
+        .and_then(|n| n.parse::<u64>().ok())
+        .filter(|count| *count > 0)
+        .unwrap_or(DEFAULT_CARD_COUNT);
+
+    Ok(HashPanTableMetadata { hash_key_id, card_count })
+}
+
+// While a key rotation is in progress an item may already carry the new hash in hash_pan and the
+// active one in previous_hash_pan, so pick whichever matches the active key
+fn select_hash_pan(item: &HashMap<String, AttributeValue>, active_key_id: Option<&str>) -> Option<String> {
+    let hash_pan = item.get("hash_pan").and_then(|v| v.as_s().ok());
+    let Some(active_key_id) = active_key_id else {
+        return hash_pan.cloned();
+    };
+
+    let key_id = item.get("hash_key_id").and_then(|v| v.as_s().ok());
+    if key_id.map(|k| k == active_key_id).unwrap_or(false) {
+        return hash_pan.cloned();
+    }
+    let previous_key_id = item.get("previous_hash_key_id").and_then(|v| v.as_s().ok());
+    if previous_key_id.map(|k| k == active_key_id).unwrap_or(false) {
+        return item.get("previous_hash_pan").and_then(|v| v.as_s().ok()).cloned();
+    }
+    None
+}
+
 pub async fn _get_random_hash_pan(dynamodb_client: &DynamoDbClient, table_name: &str, thread_id: i32) -> Result<String> {
-    // Generate random index from 0-99999 to pick from 100k records stored in our DynamoDB
-    let random_index = rand::thread_rng().gen_range(0..100000);
+    // Pick a random record from the card universe recorded by the seeder
+    let metadata = get_hash_pan_table_metadata(dynamodb_client, table_name).await?;
+    let random_index = rand::thread_rng().gen_range(0..metadata.card_count);
     
     debug!("Thread {} requesting DynamoDB record at index {}", thread_id, random_index);
     
     // Use get_item to retrieve specific record by id
+    count_dynamodb_call();
     let result = dynamodb_client
         .get_item()
         .table_name(table_name)
@@ -116,17 +186,16 @@ pub async fn _get_random_hash_pan(dynamodb_client: &DynamoDbClient, table_name:
     match result {
         Ok(output) => {
             if let Some(item) = output.item {
-                if let Some(hash_pan_attr) = item.get("hash_pan") {
-                    if let Some(hash_pan) = hash_pan_attr.as_s().ok() {
-                        info!("✅ Thread {} retrieved hash_pan from DynamoDB (index {})", thread_id, random_index);
-                        return Ok(hash_pan.clone());
-                    }
+                if let Some(hash_pan) = select_hash_pan(&item, metadata.hash_key_id.as_deref()) {
+                    info!("✅ Thread {} retrieved hash_pan from DynamoDB (index {})", thread_id, random_index);
+                    return Ok(hash_pan);
                 }
             }
             warn!("Thread {} DynamoDB record {} not found, using fallback", thread_id, random_index);
             Ok(format!("hash_{:016x}", rand::thread_rng().gen::<u64>()))
         }
         Err(e) => {
+            count_dynamodb_error();
             error!("Thread {} DynamoDB connection failed: {}, using fallback", thread_id, e);
             Ok(format!("hash_{:016x}", rand::thread_rng().gen::<u64>()))
         }
@@ -137,9 +206,13 @@ pub async fn get_100_random_hash_pans(dynamodb_client: &DynamoDbClient, table_na
 
     debug!("Thread {} requesting 1000 random hash_pans from DynamoDB", thread_id);
 
+    let metadata = get_hash_pan_table_metadata(dynamodb_client, table_name).await?;
+    debug!("Thread {} using hash key {:?} over {} cards", thread_id, metadata.hash_key_id, metadata.card_count);
+
     for _ in 0..1000 {
-        let random_index = rand::thread_rng().gen_range(0..100000);
+        let random_index = rand::thread_rng().gen_range(0..metadata.card_count);
         
+        count_dynamodb_call();
         let result = dynamodb_client
             .get_item()
             .table_name(table_name)
@@ -150,17 +223,16 @@ pub async fn get_100_random_hash_pans(dynamodb_client: &DynamoDbClient, table_na
         match result {
             Ok(output) => {
                 if let Some(item) = output.item {
-                    if let Some(hash_pan_attr) = item.get("hash_pan") {
-                        if let Some(hash_pan) = hash_pan_attr.as_s().ok() {
-                            hash_pans.push(hash_pan.clone());
-                            continue;
-                        }
+                    if let Some(hash_pan) = select_hash_pan(&item, metadata.hash_key_id.as_deref()) {
+                        hash_pans.push(hash_pan);
+                        continue;
                     }
                 }
                 // Fallback if record not found
                 hash_pans.push(format!("hash_{:016x}", rand::thread_rng().gen::<u64>()));
             }
             Err(_) => {
+                count_dynamodb_error();
                 // Fallback if DynamoDB call fails
                 hash_pans.push(format!("hash_{:016x}", rand::thread_rng().gen::<u64>()));
             }
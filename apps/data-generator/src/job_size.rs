@@ -0,0 +1,55 @@
+use anyhow::{anyhow, bail, Result};
+use std::env;
+
+pub const DEFAULT_THREADS: i32 = 3;
+pub const DEFAULT_ROWS_PER_THREAD: usize = 1_000_000;
+// Thread seeds are job_index * 1000 + thread_id
+const MAX_THREADS: i32 = 999;
+// Row seeds are thread_seed * stride + row index; 100k keeps the seeds of smaller threads as they were
+const MIN_ROW_SEED_STRIDE: u64 = 100_000;
+
+/// Threads of a job and rows each thread generates, as set by the job submitter
+#[derive(Debug, Clone, Copy)]
+pub struct JobSize {
+    pub threads: i32,
+    pub rows_per_thread: usize,
+}
+
+impl JobSize {
+    /// Parse NUM_THREADS and NUM_OF_ROWS, defaulting to 3 threads of 1,000,000 rows
+    pub fn from_env() -> Result<Self> {
+        let threads = match env::var("NUM_THREADS") {
+            Ok(value) => value.parse().map_err(|e| anyhow!("Invalid NUM_THREADS {:?}: {}", value, e))?,
+            Err(_) => DEFAULT_THREADS,
+        };
+        let rows_per_thread = match env::var("NUM_OF_ROWS") {
+            Ok(value) => value.parse().map_err(|e| anyhow!("Invalid NUM_OF_ROWS {:?}: {}", value, e))?,
+            Err(_) => DEFAULT_ROWS_PER_THREAD,
+        };
+        if !(1..=MAX_THREADS).contains(&threads) {
+            bail!("Invalid NUM_THREADS {}: must be between 1 and {}", threads, MAX_THREADS);
+        }
+        if rows_per_thread == 0 {
+            bail!("Invalid NUM_OF_ROWS: must be at least 1");
+        }
+        Ok(Self { threads, rows_per_thread })
+    }
+
+    pub fn rows_per_job(&self) -> i64 {
+        self.threads as i64 * self.rows_per_thread as i64
+    }
+
+    /// Distance between the row seeds of consecutive thread seeds, so no two threads share a seed
+    pub fn row_seed_stride(&self) -> u64 {
+        (self.rows_per_thread as u64).max(MIN_ROW_SEED_STRIDE)
+    }
+
+    pub fn row_seed(&self, thread_seed: u64, row_index: usize) -> u64 {
+        thread_seed * self.row_seed_stride() + row_index as u64
+    }
+
+    /// Row index a row seed was made from, which chargebacks reuse to match the original sequence number
+    pub fn row_index_of_seed(&self, row_seed: u64) -> i64 {
+        (row_seed % self.row_seed_stride()) as i64
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::cell::Cell;
+use std::env;
+use std::future::Future;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Run and job a log line belongs to, filled in as main learns them
+#[derive(Debug, Clone, Serialize)]
+struct JobFields {
+    run_id: Option<String>,
+    job_index: Option<i32>,
+    array_index: Option<i32>,
+    process_date: Option<String>,
+}
+
+static JOB: Mutex<JobFields> = Mutex::new(JobFields { run_id: None, job_index: None, array_index: None, process_date: None });
+
+/// Thread and table a piece of work is for
+#[derive(Debug, Clone, Copy)]
+pub struct Unit {
+    pub thread_id: i32,
+    pub table: Option<&'static str>,
+}
+
+// Rayon workers run one unit at a time, set with `enter`; async tasks carry theirs with `scope`
+thread_local! {
+    static WORKER_UNIT: Cell<Option<Unit>> = const { Cell::new(None) };
+}
+tokio::task_local! {
+    static TASK_UNIT: Unit;
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: &'static str,
+    target: &'a str,
+    message: String,
+    #[serde(flatten)]
+    job: JobFields,
+    thread_id: Option<i32>,
+    table: Option<&'static str>,
+}
+
+/// Start logging. LOG_FORMAT=json writes one JSON object per line with the run, job, thread and
+/// table fields; the default is env_logger's text. Levels come from RUST_LOG either way.
+pub fn init() -> Result<()> {
+    let mut builder = env_logger::Builder::from_default_env();
+    match env::var("LOG_FORMAT").unwrap_or_default().to_lowercase().as_str() {
+        "" | "text" => {}
+        "json" => {
+            builder.format(|buf, record| {
+                let unit = current_unit();
+                let line = JsonLine {
+                    timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                    level: record.level().as_str(),
+                    target: record.target(),
+                    message: record.args().to_string(),
+                    job: JOB.lock().map(|job| job.clone()).unwrap_or_else(|poisoned| poisoned.into_inner().clone()),
+                    thread_id: unit.map(|unit| unit.thread_id),
+                    table: unit.and_then(|unit| unit.table),
+                };
+                writeln!(buf, "{}", serde_json::to_string(&line).map_err(std::io::Error::other)?)
+            });
+        }
+        other => return Err(anyhow!("Invalid LOG_FORMAT {:?}: expected text or json", other)),
+    }
+    builder.init();
+    Ok(())
+}
+
+fn with_job(update: impl FnOnce(&mut JobFields)) {
+    let mut job = JOB.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    update(&mut job);
+}
+
+pub fn set_job(run_id: Option<&str>, job_index: i32, array_index: i32) {
+    with_job(|job| {
+        job.run_id = run_id.map(str::to_string);
+        job.job_index = Some(job_index);
+        job.array_index = Some(array_index);
+    });
+}
+
+pub fn set_process_date(process_date: &str) {
+    with_job(|job| job.process_date = Some(process_date.to_string()));
+}
+
+fn current_unit() -> Option<Unit> {
+    WORKER_UNIT.get().or_else(|| TASK_UNIT.try_with(|unit| *unit).ok())
+}
+
+/// Restores the unit a worker had before `enter`, should it pick up other work while waiting
+pub struct UnitGuard(Option<Unit>);
+
+impl Drop for UnitGuard {
+    fn drop(&mut self) {
+        WORKER_UNIT.set(self.0);
+    }
+}
+
+/// Tag the current thread's log lines with `unit` until the guard is dropped
+pub fn enter(unit: Unit) -> UnitGuard {
+    UnitGuard(WORKER_UNIT.replace(Some(unit)))
+}
+
+/// Tag the log lines of an async task with `unit`
+pub async fn scope<F: Future>(unit: Unit, future: F) -> F::Output {
+    TASK_UNIT.scope(unit, future).await
+}
@@ -1,9 +1,5 @@
 use std::env;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::Client as S3Client;
-use aws_sdk_s3::error::ProvideErrorMetadata;
 use aws_sdk_dynamodb::Client as DynamoDbClient;
 use aws_config::Region;
 use parquet::arrow::arrow_writer::ArrowWriter;
@@ -12,221 +8,83 @@ use parquet::basic::Compression;
 use arrow::record_batch::RecordBatch;
 use anyhow::Result;
 use chrono::Datelike;
-use rayon::prelude::*;
-use rand::{SeedableRng, seq::SliceRandom, rngs::StdRng};
-use log::{info, warn, error, debug};
+use log::{info, debug};
 
+mod calibration;
 mod common;
 mod dynamodb_client;
 mod data_generator;
+mod job_size;
+mod logging;
+mod metrics;
+mod partition_date;
+mod pipeline;
+mod storage;
+mod table_options;
 
-use data_generator::*;
-use dynamodb_client::get_100_random_hash_pans;
-
-#[derive(Debug)]
-struct ThreadResult {
-    authorization_batch: RecordBatch,
-    authorization_hash_batch: RecordBatch,
-    clearing_batch: RecordBatch,
-    clearing_hash_batch: RecordBatch,
-    chargeback_batch: Option<RecordBatch>,
-    chargeback_hash_batch: Option<RecordBatch>,
-}
+use job_size::JobSize;
+use partition_date::DateAllocator;
+use storage::Storage;
+use table_options::TableOptions;
 
 async fn upload_to_respective_buckets(
-    s3_client: &S3Client,
+    storage: &Storage,
     main_bucket: &str,
-    specialized_bucket: &str,
+    specialized_bucket: Option<&str>,
     table_name: &str,
     specialized_table_name: &str,
     data: Vec<u8>,
-    job_index: i32,
+    file_stem: &str,
     thread_id: i32,
     year: i32,
     month: &str,
     day: &str,
-) -> Result<()> {
-    let main_key = format!("{}/{}/{}/{}/job_{}_thread_{}.parquet", table_name, year, month, day, job_index, thread_id);
-    let specialized_key = format!("{}/{}/{}/{}/job_{}_thread_{}.parquet", specialized_table_name, year, month, day, job_index, thread_id);
-    
-    tokio::try_join!(
-        upload_with_retry(s3_client, main_bucket, &main_key, &data, 3),
-        upload_with_retry(s3_client, specialized_bucket, &specialized_key, &data, 3)
-    )?;
-    
-    Ok(())
-}
-
-async fn upload_with_retry(s3_client: &S3Client, bucket: &str, key: &str, body: &[u8], max_retries: u32) -> Result<()> {
-    for attempt in 0..max_retries {
-        let body_stream = ByteStream::from(body.to_vec());
-        match s3_client.put_object().bucket(bucket).key(key).body(body_stream).send().await {
-            Ok(_) => {
-                debug!("Upload successful: {} (attempt {})", key, attempt + 1);
-                return Ok(());
-            },
-            Err(e) => {
-                let error_details = if let Some(service_err) = e.as_service_error() {
-                    let error_code = service_err.code().unwrap_or("Unknown");
-                    let error_message = service_err.message().unwrap_or("No message");
-                    format!("Service error - Code: {}, Message: {}", error_code, error_message)
-                } else {
-                    format!("SDK error: {}", e)
-                };
-                
-                if attempt == max_retries - 1 {
-                    error!("Upload failed after {} attempts: {} - {}", max_retries, key, error_details);
-                    return Err(anyhow::anyhow!("Failed to upload {} after {} attempts. Last error: {}", key, max_retries, error_details));
-                }
-                
-                let delay = std::time::Duration::from_millis(1000 * (2_u64.pow(attempt)));
-                warn!("Upload attempt {} failed for {}: {}. Retrying in {:?}...", attempt + 1, key, error_details, delay);
-                tokio::time::sleep(delay).await;
-            }
+) -> Result<u64> {
+    let main_key = format!("{}/{}/{}/{}/{}_thread_{}.parquet", table_name, year, month, day, file_stem, thread_id);
+    let specialized_key = format!("{}/{}/{}/{}/{}_thread_{}.parquet", specialized_table_name, year, month, day, file_stem, thread_id);
+    
+    let main_upload = storage.put(main_bucket, &main_key, &data);
+    let retries = match specialized_bucket {
+        Some(specialized_bucket) => {
+            let (main_retries, specialized_retries) = tokio::try_join!(
+                main_upload,
+                storage.put(specialized_bucket, &specialized_key, &data)
+            )?;
+            main_retries + specialized_retries
         }
-    }
-    unreachable!()
+        None => main_upload.await?,
+    };
+    
+    Ok(retries)
 }
 
-fn calculate_partition_date(job_index: i32) -> (i32, String, String) {
-    let initial_load = std::env::var("INITIAL_LOAD").unwrap_or_else(|_| "true".to_string());
-    
-    if initial_load.to_lowercase() == "true" {
-        calculate_initial_partition_date(job_index)
-    } else {
-        calculate_nightly_partition_date()
+/// Start of a job's file names. Naming files after the run keeps runs that share a date from
+/// overwriting each other, and lets `job-submitter purge` find a run's files by name.
+fn output_file_stem(run_id: Option<&str>, job_index: i32) -> String {
+    match run_id {
+        Some(run_id) => format!("run_{}_job_{}", run_id, job_index),
+        None => format!("job_{}", job_index),
     }
 }
 
-fn calculate_initial_partition_date(job_index: i32) -> (i32, String, String) {
-    let start_date = chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
-    let end_date = chrono::Utc::now().naive_utc().date() - chrono::Duration::days(7);
-    let total_days = (end_date - start_date).num_days() as i32 + 1; // +1 to include end_date
-    
-    let mut hasher = DefaultHasher::new();
-    job_index.hash(&mut hasher);
-    let hash_value = hasher.finish();
-    
-    let day_offset = (hash_value % total_days as u64) as i32;
-    let target_date = start_date + chrono::Duration::days(day_offset as i64);
-    
-    (target_date.year(), format!("{:02}", target_date.month()), format!("{:02}", target_date.day()))
+/// Key of a job's entry in the partition counter's active_jobs map. Batch array children have
+/// job IDs of the form `<array job id>:<index>`.
+fn partition_order_job_id(batch_job_id: &str, array_index: i32) -> String {
+    format!("{}_{}", batch_job_id.replace(":", "_"), array_index)
 }
 
-fn calculate_nightly_partition_date() -> (i32, String, String) {
-    let initial_end_date = chrono::Utc::now().naive_utc().date() - chrono::Duration::days(7);
-    let days_since_initial_end = (chrono::Utc::now().naive_utc().date() - initial_end_date).num_days();
-    
-    // Generate dates from (initial_end_date + 1) to today
-    let nightly_job_index = std::env::var("AWS_BATCH_JOB_ARRAY_INDEX")
+fn calculate_partition_date(job_index: i32) -> Result<(i32, String, String)> {
+    // Brands of a brand mix with block or weighted allocation restart it at their first job
+    let date_job_index_offset: i32 = env::var("DATE_JOB_INDEX_OFFSET")
         .unwrap_or_else(|_| "0".to_string())
-        .parse::<i32>()
-        .unwrap_or(0);
-    
-    let day_offset = nightly_job_index % days_since_initial_end as i32;
-    let target_date = initial_end_date + chrono::Duration::days(day_offset as i64 + 1);
-    
-    (target_date.year(), format!("{:02}", target_date.month()), format!("{:02}", target_date.day()))
-}
-
-async fn upload_thread_results(
-    s3_client: &S3Client,
-    buckets: &(String, String, String, String),
-    thread_result: ThreadResult,
-    job_index: i32,
-    thread_id: i32,
-    year: i32,
-    month: &str,
-    day: &str,
-) -> Result<()> {
-    let (payment_data_bucket, auth_bucket, clearing_bucket, chargeback_bucket) = buckets;
-    
-    let mut upload_tasks = vec![];
-    
-    upload_tasks.push(tokio::spawn({
-        let s3_client = s3_client.clone();
-        let payment_data_bucket = payment_data_bucket.clone();
-        let auth_bucket = auth_bucket.clone();
-        let month = month.to_string();
-        let day = day.to_string();
-        async move {
-            let data = generate_parquet_data(thread_result.authorization_batch).await?;
-            upload_to_respective_buckets(&s3_client, &payment_data_bucket, &auth_bucket, "authorization", "authorization", data, job_index, thread_id, year, &month, &day).await
-        }
-    }));
-    
-    upload_tasks.push(tokio::spawn({
-        let s3_client = s3_client.clone();
-        let payment_data_bucket = payment_data_bucket.clone();
-        let auth_bucket = auth_bucket.clone();
-        let month = month.to_string();
-        let day = day.to_string();
-        async move {
-            let data = generate_parquet_data(thread_result.authorization_hash_batch).await?;
-            upload_to_respective_buckets(&s3_client, &payment_data_bucket, &auth_bucket, "authorization_hash", "authorization_hash", data, job_index, thread_id, year, &month, &day).await
-        }
-    }));
-    
-    upload_tasks.push(tokio::spawn({
-        let s3_client = s3_client.clone();
-        let payment_data_bucket = payment_data_bucket.clone();
-        let clearing_bucket = clearing_bucket.clone();
-        let month = month.to_string();
-        let day = day.to_string();
-        async move {
-            let data = generate_parquet_data(thread_result.clearing_batch).await?;
-            upload_to_respective_buckets(&s3_client, &payment_data_bucket, &clearing_bucket, "clearing", "clearing", data, job_index, thread_id, year, &month, &day).await
-        }
-    }));
-    
-    upload_tasks.push(tokio::spawn({
-        let s3_client = s3_client.clone();
-        let payment_data_bucket = payment_data_bucket.clone();
-        let clearing_bucket = clearing_bucket.clone();
-        let month = month.to_string();
-        let day = day.to_string();
-        async move {
-            let data = generate_parquet_data(thread_result.clearing_hash_batch).await?;
-            upload_to_respective_buckets(&s3_client, &payment_data_bucket, &clearing_bucket, "clearing_hash", "clearing_hash", data, job_index, thread_id, year, &month, &day).await
-        }
-    }));
-    
-    if let (Some(chargeback_batch), Some(chargeback_hash_batch)) = (thread_result.chargeback_batch, thread_result.chargeback_hash_batch) {
-        upload_tasks.push(tokio::spawn({
-            let s3_client = s3_client.clone();
-            let payment_data_bucket = payment_data_bucket.clone();
-            let chargeback_bucket = chargeback_bucket.clone();
-            let month = month.to_string();
-            let day = day.to_string();
-            async move {
-                let data = generate_parquet_data(chargeback_batch).await?;
-                upload_to_respective_buckets(&s3_client, &payment_data_bucket, &chargeback_bucket, "chargeback", "chargeback", data, job_index, thread_id, year, &month, &day).await
-            }
-        }));
-        
-        upload_tasks.push(tokio::spawn({
-            let s3_client = s3_client.clone();
-            let payment_data_bucket = payment_data_bucket.clone();
-            let chargeback_bucket = chargeback_bucket.clone();
-            let month = month.to_string();
-            let day = day.to_string();
-            async move {
-                let data = generate_parquet_data(chargeback_hash_batch).await?;
-                upload_to_respective_buckets(&s3_client, &payment_data_bucket, &chargeback_bucket, "chargeback_hash", "chargeback_hash", data, job_index, thread_id, year, &month, &day).await
-            }
-        }));
-    }
-    
-    for task in upload_tasks {
-        task.await??;
-    }
-    
-    Ok(())
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid DATE_JOB_INDEX_OFFSET: {}", e))?;
+    let target_date = DateAllocator::from_env()?.date_for_job(job_index - date_job_index_offset)?;
+    Ok((target_date.year(), format!("{:02}", target_date.month()), format!("{:02}", target_date.day())))
 }
 
-async fn generate_parquet_data(
-    batch: arrow::record_batch::RecordBatch,
-) -> Result<Vec<u8>> {
+/// Encode a batch as Snappy-compressed Parquet
+fn generate_parquet_data(batch: RecordBatch) -> Result<Vec<u8>> {
     let mut buffer = Vec::new();
     let props = WriterProperties::builder()
         .set_compression(Compression::SNAPPY)
@@ -241,106 +99,27 @@ async fn generate_parquet_data(
     Ok(buffer)
 }
 
-async fn generate_thread_data(
-    thread_id: i32,
-    job_index: i32,
-    partition_job_order: i64,
-    num_threads: i32,
-    process_date: String,
-    dynamodb_client: DynamoDbClient,
-    hash_pan_table_name: String,
-    num_rows: usize,
-    chargeback_percentage: f64,
-) -> Result<ThreadResult> {
-    info!("🔄 Thread {} starting complete table generation for {} rows", thread_id, num_rows);
-    
-    // Calculate unique thread seed to prevent collisions across jobs and threads
-    let thread_seed = (job_index as u64) * 1000 + (thread_id as u64);
-    
-    // Get 100 hash_pans for this thread to randomly select from during generation
-    debug!("Thread {} retrieving 100 hash_pans from DynamoDB", thread_id);
-    let hash_pan_pool = get_100_random_hash_pans(&dynamodb_client, &hash_pan_table_name, thread_id).await?;
-    info!("🔑 Thread {} retrieved {} hash_pans for random selection", thread_id, hash_pan_pool.len());
-    
-    // Generate unique row seeds for this thread (job_index * 1000 * 100000 + thread_id * 100000 + row_idx)
-    let row_seeds: Vec<u64> = (0..num_rows).map(|row_idx| {
-        thread_seed * 100000 + (row_idx as u64)
-    }).collect();
-    
-    // Generate chargeback row seeds by selecting from authorization transactions
-    let total_chargebacks_needed = (num_rows as f64 * chargeback_percentage) as usize;
-    let chargeback_row_seeds = if total_chargebacks_needed > 0 {
-        let chargeback_seed = (job_index as u64).wrapping_mul(1000).wrapping_add(thread_id as u64);
-        let mut rng = StdRng::seed_from_u64(chargeback_seed);
-        
-        row_seeds.choose_multiple(&mut rng, total_chargebacks_needed)
-            .cloned()
-            .collect::<Vec<u64>>()
-    } else {
-        Vec::new()
-    };
-    
-    // Generate all 6 tables in parallel using Rayon
-    info!("✅ Thread {} starting parallel generation of all 6 tables", thread_id);
-    
-    let ((authorization_batch, authorization_hash_batch), ((clearing_batch, clearing_hash_batch), (chargeback_batch, chargeback_hash_batch))) = rayon::join(
-        || {
-            let auth_batch = generate_authorization_batch(num_rows, &row_seeds, partition_job_order, thread_id, num_threads, &chargeback_row_seeds, &hash_pan_pool, &process_date);
-            info!("✅ Thread {} completed authorization table ({} rows)", thread_id, auth_batch.num_rows());
-            let auth_hash_batch = generate_authorization_hash_batch(num_rows, &row_seeds, partition_job_order, thread_id, num_threads, &chargeback_row_seeds, &hash_pan_pool, &process_date);
-            info!("✅ Thread {} completed authorization_hash table ({} rows)", thread_id, auth_hash_batch.num_rows());
-            (auth_batch, auth_hash_batch)
-        },
-        || {
-            rayon::join(
-                || {
-                    let clearing_batch = generate_clearing_batch(num_rows, &row_seeds, partition_job_order, thread_id, num_threads, &chargeback_row_seeds, &hash_pan_pool, &process_date);
-                    info!("✅ Thread {} completed clearing table ({} rows)", thread_id, clearing_batch.num_rows());
-                    let clearing_hash_batch = generate_clearing_hash_batch(num_rows, &row_seeds, partition_job_order, thread_id, num_threads, &chargeback_row_seeds, &hash_pan_pool, &process_date);
-                    info!("✅ Thread {} completed clearing_hash table ({} rows)", thread_id, clearing_hash_batch.num_rows());
-                    (clearing_batch, clearing_hash_batch)
-                },
-                || {
-                    if !chargeback_row_seeds.is_empty() {
-                        info!("✅ Thread {} generating chargeback tables for {} transactions", thread_id, chargeback_row_seeds.len());
-                        let (cb_batch, cb_hash_batch) = rayon::join(
-                            || {
-                                let batch = generate_chargeback_batch(chargeback_row_seeds.len(), &chargeback_row_seeds, partition_job_order, thread_id, num_threads, &hash_pan_pool, &process_date);
-                                info!("✅ Thread {} completed chargeback table ({} rows)", thread_id, batch.num_rows());
-                                batch
-                            },
-                            || {
-                                let batch = generate_chargeback_hash_batch(chargeback_row_seeds.len(), &chargeback_row_seeds, partition_job_order, thread_id, num_threads, &hash_pan_pool, &process_date);
-                                info!("✅ Thread {} completed chargeback_hash table ({} rows)", thread_id, batch.num_rows());
-                                batch
-                            }
-                        );
-                        (Some(cb_batch), Some(cb_hash_batch))
-                    } else {
-                        info!("✅ Thread {} skipping chargeback tables (no chargeback transactions)", thread_id);
-                        (None, None)
-                    }
-                }
-            )
-        }
-    );
-    
-    info!("✅ Thread {} completed all table generation", thread_id);
-    
-    Ok(ThreadResult {
-        authorization_batch,
-        authorization_hash_batch,
-        clearing_batch,
-        clearing_hash_batch,
-        chargeback_batch,
-        chargeback_hash_batch,
-    })
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::init();
+    logging::init()?;
+
+    // GENERATOR_MODE=calibrate measures bytes per row locally and prints them as JSON for the job submitter
+    if env::var("GENERATOR_MODE").map(|mode| mode.to_lowercase() == "calibrate").unwrap_or(false) {
+        let sample_rows = env::var("CALIBRATION_ROWS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(100000);
+        let calibration = calibration::run_calibration(sample_rows).await?;
+        println!("{}", serde_json::to_string_pretty(&calibration)?);
+        return Ok(());
+    }
+
+    // GENERATOR_MODE=barrier does nothing; the job submitter chains these between a step and
+    // more array jobs than Batch lets one job depend on
+    if env::var("GENERATOR_MODE").map(|mode| mode.to_lowercase() == "barrier").unwrap_or(false) {
+        info!("🔗 Barrier job of run {} reached", env::var("RUN_ID").unwrap_or_else(|_| "unknown".to_string()));
+        return Ok(());
+    }
     
     let array_index: i32 = env::var("AWS_BATCH_JOB_ARRAY_INDEX")
         .unwrap_or_else(|_| "0".to_string())
@@ -353,9 +132,12 @@ async fn main() -> Result<()> {
         .expect("Invalid job index offset");
     
     let job_index = array_index + job_index_offset;
+    let run_id = env::var("RUN_ID").ok();
+    logging::set_job(run_id.as_deref(), job_index, array_index);
     
     info!("🔍 Job index calculation: AWS_BATCH_JOB_ARRAY_INDEX={}, JOB_INDEX_OFFSET={}, final_job_index={}", array_index, job_index_offset, job_index);
-    info!("🚀 Job {} starting with array_index={}, offset={}", job_index, array_index, job_index_offset);
+    info!("🚀 Job {} of run {} starting with array_index={}, offset={}", job_index, run_id.as_deref().unwrap_or("unknown"), array_index, job_index_offset);
+    let file_stem = output_file_stem(run_id.as_deref(), job_index);
     
     let payment_data_bucket_name = env::var("PAYMENT_DATA_BUCKET_NAME").expect("PAYMENT_DATA_BUCKET_NAME not set");
     let clearing_bucket_name = env::var("CLEARING_BUCKET_NAME").expect("CLEARING_BUCKET_NAME not set");
@@ -364,10 +146,7 @@ async fn main() -> Result<()> {
     let hash_pan_table_name = env::var("HASH_PAN_TABLE_NAME").expect("HASH_PAN_TABLE_NAME not set");
     let partition_counter_table_name = env::var("PARTITION_COUNTER_TABLE_NAME").expect("PARTITION_COUNTER_TABLE_NAME not set");
     
-    let num_rows = env::var("NUM_OF_ROWS")
-        .unwrap_or_else(|_| "250000".to_string())
-        .parse::<usize>()
-        .unwrap_or(250000);
+    let job_size = JobSize::from_env()?;
     
     let chargeback_percentage = env::var("CHARGEBACK_PERCENTAGE")
         .unwrap_or_else(|_| "0.1".to_string())
@@ -377,13 +156,17 @@ async fn main() -> Result<()> {
     let aws_region = env::var("AWS_DEFAULT_REGION").unwrap_or_else(|_| "us-east-1".to_string());
     let dynamodb_region = env::var("DYNAMODB_REGION").unwrap_or_else(|_| aws_region.clone());
     
-    info!("⚙️ Job {} configuration: rows={}, chargeback_pct={:.1}%, region={}", 
-          job_index, num_rows, chargeback_percentage * 100.0, aws_region);
+    let tables = TableOptions::from_env()?;
+    
+    info!("⚙️ Job {} configuration: threads={}, rows={}, chargeback_pct={:.1}%, region={}", 
+          job_index, job_size.threads, job_size.rows_per_thread, chargeback_percentage * 100.0, aws_region);
+    debug!("Table options: {:?}", tables);
     
     let start_time = std::time::Instant::now();
     
-    let (year, month, day) = calculate_partition_date(job_index);
+    let (year, month, day) = calculate_partition_date(job_index)?;
     let process_date = format!("{}-{:02}-{:02}", year, month.parse::<u32>().unwrap_or(1), day.parse::<u32>().unwrap_or(1));
+    logging::set_process_date(&process_date);
     info!("📅 Job {} partition date: {}-{}-{} (process_date: {})", job_index, year, month, day, process_date);
     
     let gen_start = std::time::Instant::now();
@@ -409,7 +192,14 @@ async fn main() -> Result<()> {
         .await;
     info!("✅ S3 client configured for region {}", aws_region);
     
-    let s3_client = S3Client::new(&s3_config);
+    // OUTPUT_DIR keeps every file on local disk, e.g. for job-submitter --local runs
+    let storage = match env::var("OUTPUT_DIR") {
+        Ok(output_dir) => {
+            info!("💾 Writing files under {} instead of S3", output_dir);
+            Storage::Local(output_dir.into())
+        }
+        Err(_) => Storage::S3(S3Client::new(&s3_config)),
+    };
     
     debug!("Loading DynamoDB configuration...");
     let dynamodb_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
@@ -423,44 +213,59 @@ async fn main() -> Result<()> {
     let dynamodb_client = DynamoDbClient::new(&dynamodb_config);
     
     // Create unique job identifier (replace colon with underscore for DynamoDB)
-    let job_id = format!("{}_{}", 
-        env::var("AWS_BATCH_JOB_ID").unwrap_or_else(|_| "local".to_string()).replace(":", "_"),
-        array_index
-    );
+    let job_id = match env::var("RETRY_OF_ARRAY_JOB_ID") {
+        // A resubmitted job claims its partition order under the identifier of the child it replaces
+        Ok(original_array_job_id) => {
+            let original_offset: i32 = env::var("RETRY_OF_JOB_INDEX_OFFSET")
+                .map_err(|_| anyhow::anyhow!("RETRY_OF_JOB_INDEX_OFFSET must be set with RETRY_OF_ARRAY_JOB_ID"))?
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid RETRY_OF_JOB_INDEX_OFFSET: {}", e))?;
+            let original_array_index = job_index - original_offset;
+            // A single job was submitted without array properties: its ID has no index suffix
+            // and it claimed under array index 0
+            if env::var("RETRY_OF_ARRAY_CHILD").map_or(true, |value| value != "false") {
+                partition_order_job_id(&format!("{}:{}", original_array_job_id, original_array_index), original_array_index)
+            } else {
+                partition_order_job_id(&original_array_job_id, 0)
+            }
+        }
+        Err(_) => partition_order_job_id(&env::var("AWS_BATCH_JOB_ID").unwrap_or_else(|_| "local".to_string()), array_index),
+    };
     info!("🆔 Job unique identifier: {}", job_id);
     
     // Get atomic partition job order from DynamoDB
     info!("🔢 Getting partition job order for {} from DynamoDB...", process_date);
     info!("🔍 Using partition counter table: {}", partition_counter_table_name);
-    let partition_job_order = dynamodb_client::get_partition_job_order(&process_date, &dynamodb_client, &partition_counter_table_name, &job_id)
+    let partition_job_order = dynamodb_client::get_partition_job_order(&process_date, &dynamodb_client, &partition_counter_table_name, &job_id, run_id.as_deref())
         .await?;
     info!("✅ Job {} assigned partition order {} for date {}", job_index, partition_job_order, process_date);
     
     info!("🚀 Job {} starting parallel thread generation", job_index);
-    let available_threads = rayon::current_num_threads();
-    let num_threads = 3;
-    info!("🧵 Job {} using {} threads (forced, available: {}), {} rows per thread", job_index, num_threads, available_threads, num_rows);
-    
-    let buckets = (payment_data_bucket_name, authorization_bucket_name, clearing_bucket_name, chargeback_bucket_name);
-    
-    (1..=num_threads)
-        .into_par_iter()
-        .map(|thread_id| {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                let thread_result = generate_thread_data(
-                    thread_id as i32, job_index, partition_job_order, num_threads, process_date.clone(),
-                    dynamodb_client.clone(), hash_pan_table_name.clone(), 
-                    num_rows, chargeback_percentage
-                ).await?;
-                
-                upload_thread_results(
-                    &s3_client, &buckets, thread_result, 
-                    job_index, thread_id as i32, year, &month, &day
-                ).await
-            })
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    let num_threads = job_size.threads;
+    info!("🧵 Job {} using {} threads (NUM_THREADS) on {} CPUs, {} rows per thread", job_index, num_threads, rayon::current_num_threads(), job_size.rows_per_thread);
+    
+    let context = pipeline::JobContext {
+        job_index,
+        partition_job_order,
+        job_size,
+        process_date: process_date.clone(),
+        chargeback_rate: chargeback_percentage,
+        tables,
+    };
+    let output = pipeline::Output {
+        storage,
+        payment_data_bucket: payment_data_bucket_name,
+        authorization_bucket: authorization_bucket_name,
+        clearing_bucket: clearing_bucket_name,
+        chargeback_bucket: chargeback_bucket_name,
+        file_stem,
+        year,
+        month,
+        day,
+    };
+    let mut summary = metrics::JobSummary::new(run_id.clone(), job_index, process_date.clone(), partition_job_order, job_size.threads, job_size.rows_per_thread);
+    let setup_seconds = start_time.elapsed().as_secs_f64();
+    let records = pipeline::run_job(context, output, &dynamodb_client, &hash_pan_table_name).await?;
     
     // Mark job as completed and remove from active_jobs
     info!("🏁 Marking job {} as completed", job_id);
@@ -468,5 +273,9 @@ async fn main() -> Result<()> {
     
     info!("✅ Job {} completed all {} threads in {:.1}s", job_index, num_threads, gen_start.elapsed().as_secs_f64());
     info!("🎉 Job {} finished successfully in {:.1}s", job_index, start_time.elapsed().as_secs_f64());
+    
+    summary.finish(&records, setup_seconds, start_time.elapsed().as_secs_f64());
+    info!("📊 Job {} wrote {} rows at {:.0} rows/s, {:.1} MB/s", job_index, summary.totals.rows, summary.rows_per_second, summary.megabytes_per_second);
+    summary.emit()?;
     Ok(())
 }
@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::output_file_stem;
+
+// CloudWatch namespace of the embedded metric format lines
+const EMF_NAMESPACE: &str = "DataGenerator";
+
+// Reads one value of a table's totals for the OpenMetrics output
+type TotalsField = fn(&Totals) -> f64;
+
+// Counted where the calls are made, so call sites need no handle to the job's metrics
+static DYNAMODB_CALLS: AtomicU64 = AtomicU64::new(0);
+static DYNAMODB_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+pub fn count_dynamodb_call() {
+    DYNAMODB_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A failed call the generator recovered from, e.g. with a fallback hash_pan
+pub fn count_dynamodb_error() {
+    DYNAMODB_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// One thread's table, from generation to its last upload
+#[derive(Debug, Clone, Default)]
+pub struct TableRecord {
+    pub table: &'static str,
+    pub rows: u64,
+    pub bytes: u64,
+    /// Copies written: the payment data bucket, plus the specialized bucket if enabled
+    pub files: u64,
+    pub generate_seconds: f64,
+    pub encode_seconds: f64,
+    pub upload_seconds: f64,
+    pub upload_retries: u64,
+}
+
+/// Totals of a job or of one table across the job's threads
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub rows: u64,
+    pub bytes: u64,
+    pub bytes_written: u64,
+    pub files: u64,
+    pub generate_seconds: f64,
+    pub encode_seconds: f64,
+    pub upload_seconds: f64,
+    pub upload_retries: u64,
+}
+
+impl Totals {
+    fn add(&mut self, record: &TableRecord) {
+        self.rows += record.rows;
+        self.bytes += record.bytes;
+        self.bytes_written += record.bytes * record.files;
+        self.files += record.files;
+        self.generate_seconds += record.generate_seconds;
+        self.encode_seconds += record.encode_seconds;
+        self.upload_seconds += record.upload_seconds;
+        self.upload_retries += record.upload_retries;
+    }
+}
+
+/// Final metrics of a job, printed to stdout as one JSON line starting with `"metrics":"job_summary"`.
+/// Seconds per phase are summed over threads, so they exceed `wall_seconds` when threads overlap.
+#[derive(Debug, Serialize)]
+pub struct JobSummary {
+    pub metrics: &'static str,
+    pub run_id: Option<String>,
+    pub job_index: i32,
+    pub process_date: String,
+    pub partition_job_order: i64,
+    pub threads: i32,
+    pub rows_per_thread: usize,
+    #[serde(flatten)]
+    pub totals: Totals,
+    pub dynamodb_calls: u64,
+    pub dynamodb_errors: u64,
+    pub setup_seconds: f64,
+    pub wall_seconds: f64,
+    pub rows_per_second: f64,
+    pub megabytes_per_second: f64,
+    pub tables: BTreeMap<&'static str, Totals>,
+}
+
+impl JobSummary {
+    pub fn new(run_id: Option<String>, job_index: i32, process_date: String, partition_job_order: i64, threads: i32, rows_per_thread: usize) -> Self {
+        Self {
+            metrics: "job_summary",
+            run_id,
+            job_index,
+            process_date,
+            partition_job_order,
+            threads,
+            rows_per_thread,
+            totals: Totals::default(),
+            dynamodb_calls: 0,
+            dynamodb_errors: 0,
+            setup_seconds: 0.0,
+            wall_seconds: 0.0,
+            rows_per_second: 0.0,
+            megabytes_per_second: 0.0,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Fill in the table records, the DynamoDB counters and the throughput over `wall_seconds`
+    pub fn finish(&mut self, records: &[TableRecord], setup_seconds: f64, wall_seconds: f64) {
+        for record in records {
+            self.totals.add(record);
+            self.tables.entry(record.table).or_default().add(record);
+        }
+        self.dynamodb_calls = DYNAMODB_CALLS.load(Ordering::Relaxed);
+        self.dynamodb_errors = DYNAMODB_ERRORS.load(Ordering::Relaxed);
+        self.setup_seconds = setup_seconds;
+        self.wall_seconds = wall_seconds;
+        if wall_seconds > 0.0 {
+            self.rows_per_second = self.totals.rows as f64 / wall_seconds;
+            self.megabytes_per_second = self.totals.bytes_written as f64 / 1_000_000.0 / wall_seconds;
+        }
+    }
+
+    /// Print the JSON summary line, then the extra formats METRICS_FORMAT and METRICS_DIR ask for
+    pub fn emit(&self) -> Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+
+        let formats = env::var("METRICS_FORMAT").unwrap_or_default().to_lowercase();
+        for format in formats.split(',').map(str::trim).filter(|format| !format.is_empty()) {
+            match format {
+                "json" => {}
+                "emf" => {
+                    for line in self.emf_lines() {
+                        println!("{}", line);
+                    }
+                }
+                other => return Err(anyhow!("Invalid METRICS_FORMAT {:?}: expected json or emf", other)),
+            }
+        }
+
+        if let Ok(metrics_dir) = env::var("METRICS_DIR") {
+            let dir = PathBuf::from(metrics_dir);
+            std::fs::create_dir_all(&dir)?;
+            let path = dir.join(format!("{}.prom", output_file_stem(self.run_id.as_deref(), self.job_index)));
+            std::fs::write(&path, self.openmetrics()).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// CloudWatch embedded metric format: one line for the job and one per table. The run and
+    /// job are properties rather than dimensions, so they stay searchable without a metric each.
+    fn emf_lines(&self) -> Vec<String> {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let line = |dimensions: serde_json::Value, mut values: serde_json::Map<String, serde_json::Value>, names: &[(&str, &str)]| {
+            let metrics: Vec<_> = names.iter().map(|(name, unit)| json!({ "Name": name, "Unit": unit })).collect();
+            values.insert("_aws".to_string(), json!({
+                "Timestamp": timestamp,
+                "CloudWatchMetrics": [{ "Namespace": EMF_NAMESPACE, "Dimensions": dimensions, "Metrics": metrics }],
+            }));
+            values.insert("RunId".to_string(), json!(self.run_id));
+            values.insert("JobIndex".to_string(), json!(self.job_index));
+            values.insert("ProcessDate".to_string(), json!(self.process_date));
+            serde_json::Value::Object(values).to_string()
+        };
+        let totals_values = |totals: &Totals| {
+            let mut values = serde_json::Map::new();
+            values.insert("Rows".to_string(), json!(totals.rows));
+            values.insert("BytesWritten".to_string(), json!(totals.bytes_written));
+            values.insert("GenerateSeconds".to_string(), json!(totals.generate_seconds));
+            values.insert("EncodeSeconds".to_string(), json!(totals.encode_seconds));
+            values.insert("UploadSeconds".to_string(), json!(totals.upload_seconds));
+            values.insert("UploadRetries".to_string(), json!(totals.upload_retries));
+            values
+        };
+        let totals_metrics = [
+            ("Rows", "Count"),
+            ("BytesWritten", "Bytes"),
+            ("GenerateSeconds", "Seconds"),
+            ("EncodeSeconds", "Seconds"),
+            ("UploadSeconds", "Seconds"),
+            ("UploadRetries", "Count"),
+        ];
+
+        let mut job_values = totals_values(&self.totals);
+        job_values.insert("DynamoDbCalls".to_string(), json!(self.dynamodb_calls));
+        job_values.insert("DynamoDbErrors".to_string(), json!(self.dynamodb_errors));
+        job_values.insert("WallSeconds".to_string(), json!(self.wall_seconds));
+        job_values.insert("RowsPerSecond".to_string(), json!(self.rows_per_second));
+        let mut job_metrics = totals_metrics.to_vec();
+        job_metrics.extend([("DynamoDbCalls", "Count"), ("DynamoDbErrors", "Count"), ("WallSeconds", "Seconds"), ("RowsPerSecond", "Count/Second")]);
+
+        let mut lines = vec![line(json!([[]]), job_values, &job_metrics)];
+        for (table, totals) in &self.tables {
+            let mut values = totals_values(totals);
+            values.insert("Table".to_string(), json!(table));
+            lines.push(line(json!([["Table"]]), values, &totals_metrics));
+        }
+        lines
+    }
+
+    /// OpenMetrics text exposition, one sample per table for table metrics
+    fn openmetrics(&self) -> String {
+        let labels = format!("run_id=\"{}\",job_index=\"{}\",process_date=\"{}\"", self.run_id.as_deref().unwrap_or(""), self.job_index, self.process_date);
+        let mut text = String::new();
+        let table_counters: [(&str, &str, TotalsField); 7] = [
+            ("datagen_rows", "Rows generated", |t| t.rows as f64),
+            ("datagen_written_bytes", "Bytes written across all copies", |t| t.bytes_written as f64),
+            ("datagen_files", "Files written", |t| t.files as f64),
+            ("datagen_generate_seconds", "Time generating rows, summed over threads", |t| t.generate_seconds),
+            ("datagen_encode_seconds", "Time encoding Parquet, summed over threads", |t| t.encode_seconds),
+            ("datagen_upload_seconds", "Time uploading, summed over threads", |t| t.upload_seconds),
+            ("datagen_upload_retries", "Upload attempts retried", |t| t.upload_retries as f64),
+        ];
+        for (name, help, value) in table_counters {
+            let _ = writeln!(text, "# TYPE {} counter", name);
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            for (table, totals) in &self.tables {
+                let _ = writeln!(text, "{}_total{{{},table=\"{}\"}} {}", name, labels, table, value(totals));
+            }
+        }
+        let job_values = [
+            ("datagen_dynamodb_calls", "counter", "DynamoDB requests made", self.dynamodb_calls as f64),
+            ("datagen_dynamodb_errors", "counter", "DynamoDB requests that failed and fell back", self.dynamodb_errors as f64),
+            ("datagen_job_wall_seconds", "gauge", "Job run time from start to last upload", self.wall_seconds),
+            ("datagen_job_setup_seconds", "gauge", "Time before the pipeline started, mostly partition coordination in DynamoDB", self.setup_seconds),
+            ("datagen_job_rows_per_second", "gauge", "Rows generated per second of wall time", self.rows_per_second),
+        ];
+        for (name, kind, help, value) in job_values {
+            let _ = writeln!(text, "# TYPE {} {}", name, kind);
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let suffix = if kind == "counter" { "_total" } else { "" };
+            let _ = writeln!(text, "{}{}{{{}}} {}", name, suffix, labels, value);
+        }
+        text.push_str("# EOF\n");
+        text
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::env;
+
+// Profile weights are compared as integers so every toolchain allocates identically
+const WEIGHT_SCALE: f64 = 1000.0;
+
+/// Relative job volume by weekday (Monday first) and by month (January first)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateProfile {
+    pub day_of_week: [f64; 7],
+    pub month: [f64; 12],
+}
+
+impl DateProfile {
+    fn day_weight(&self, date: NaiveDate) -> u64 {
+        let scale = |weight: f64| (weight.max(0.0) * WEIGHT_SCALE).round() as u64;
+        scale(self.day_of_week[date.weekday().num_days_from_monday() as usize]) * scale(self.month[date.month0() as usize])
+    }
+}
+
+/// Maps a job index onto a partition date; mirrors the job submitter's allocator so dry-run plans
+/// match what jobs actually write
+pub enum DateAllocator {
+    /// Job i lands on start + i / jobs_per_day
+    Block { start: NaiveDate, end: NaiveDate, jobs_per_day: i32 },
+    /// Job i lands on start + i % days
+    RoundRobin { start: NaiveDate, end: NaiveDate },
+    /// Days get jobs in proportion to their profile weight (largest remainder), in date order
+    Weighted { start: NaiveDate, end: NaiveDate, cumulative_jobs: Vec<u64> },
+}
+
+impl DateAllocator {
+    /// Build the allocator from PARTITION_START_DATE, PARTITION_END_DATE, DATE_ALLOCATION and its
+    /// parameters (JOBS_PER_DAY, or TOTAL_JOBS and DATE_PROFILE). Without an explicit window the
+    /// INITIAL_LOAD window is derived from the clock, as submitters before explicit windows expect.
+    pub fn from_env() -> Result<Self> {
+        let (start, end) = match env::var("PARTITION_START_DATE") {
+            Ok(start) => {
+                let end = env::var("PARTITION_END_DATE")
+                    .map_err(|_| anyhow!("PARTITION_END_DATE must be set with PARTITION_START_DATE"))?;
+                (parse_date("PARTITION_START_DATE", &start)?, parse_date("PARTITION_END_DATE", &end)?)
+            }
+            Err(_) => default_window(),
+        };
+        if end < start {
+            bail!("PARTITION_END_DATE {} is before PARTITION_START_DATE {}", end, start);
+        }
+
+        let allocation = env::var("DATE_ALLOCATION").unwrap_or_else(|_| {
+            if env::var("JOBS_PER_DAY").is_ok() { "block".to_string() } else { "round_robin".to_string() }
+        });
+        match allocation.as_str() {
+            "block" => {
+                let jobs_per_day = parse_env::<i32>("JOBS_PER_DAY")?;
+                if jobs_per_day < 1 {
+                    bail!("JOBS_PER_DAY must be at least 1");
+                }
+                Ok(DateAllocator::Block { start, end, jobs_per_day })
+            }
+            "round_robin" => Ok(DateAllocator::RoundRobin { start, end }),
+            "weighted" => {
+                let total_jobs = parse_env::<u64>("TOTAL_JOBS")?;
+                let profile: DateProfile = serde_json::from_str(&env::var("DATE_PROFILE").map_err(|_| anyhow!("DATE_PROFILE must be set for weighted allocation"))?)
+                    .map_err(|e| anyhow!("Invalid DATE_PROFILE: {}", e))?;
+                Self::weighted(start, end, total_jobs, &profile)
+            }
+            other => bail!("Unsupported DATE_ALLOCATION: {}. Supported: block, round_robin, weighted", other),
+        }
+    }
+
+    fn weighted(start: NaiveDate, end: NaiveDate, total_jobs: u64, profile: &DateProfile) -> Result<Self> {
+        let weights: Vec<u64> = start.iter_days().take_while(|date| *date <= end).map(|date| profile.day_weight(date)).collect();
+        let total_weight: u128 = weights.iter().map(|w| *w as u128).sum();
+        if total_weight == 0 {
+            bail!("DATE_PROFILE gives every day between {} and {} a weight of 0", start, end);
+        }
+
+        let total = total_jobs as u128;
+        let mut counts: Vec<u64> = weights.iter().map(|w| (total * *w as u128 / total_weight) as u64).collect();
+        let mut remainders: Vec<(u128, usize)> = weights.iter().enumerate().map(|(day, w)| (total * *w as u128 % total_weight, day)).collect();
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        let unassigned = total_jobs - counts.iter().sum::<u64>();
+        for (_, day) in remainders.iter().take(unassigned as usize) {
+            counts[*day] += 1;
+        }
+
+        let cumulative_jobs = counts
+            .iter()
+            .scan(0, |sum, count| {
+                *sum += count;
+                Some(*sum)
+            })
+            .collect();
+        Ok(DateAllocator::Weighted { start, end, cumulative_jobs })
+    }
+
+    pub fn date_for_job(&self, job_index: i32) -> Result<NaiveDate> {
+        let (start, end, day) = match self {
+            DateAllocator::Block { start, end, jobs_per_day } => (*start, *end, (job_index / jobs_per_day) as i64),
+            DateAllocator::RoundRobin { start, end } => (*start, *end, (job_index as i64) % ((*end - *start).num_days() + 1)),
+            DateAllocator::Weighted { start, end, cumulative_jobs } => {
+                (*start, *end, cumulative_jobs.partition_point(|jobs| *jobs <= job_index as u64) as i64)
+            }
+        };
+        let date = start + chrono::Duration::days(day);
+        if date > end {
+            bail!("Job {} falls after the end of the {} to {} window", job_index, start, end);
+        }
+        Ok(date)
+    }
+}
+
+fn default_window() -> (NaiveDate, NaiveDate) {
+    let today = chrono::Utc::now().naive_utc().date();
+    let initial_load = env::var("INITIAL_LOAD").unwrap_or_else(|_| "true".to_string());
+    if initial_load.to_lowercase() == "true" {
+        (NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(), today - chrono::Duration::days(7))
+    } else {
+        (today - chrono::Duration::days(6), today)
+    }
+}
+
+fn parse_date(name: &str, value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|e| anyhow!("Invalid {} {}: {}", name, value, e))
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    env::var(name)
+        .map_err(|_| anyhow!("{} env var must be set", name))?
+        .parse::<T>()
+        .map_err(|e| anyhow!("Invalid {}: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The job submitter's date_allocation.rs pins the same tables; a change to either allocator
+    // must keep both passing, or dry-run plans stop matching what jobs write.
+    // Jobs 0..=21 over Monday 2024-01-29 to Sunday 2024-02-04; None is a job past the window
+    const BLOCK_3_PER_DAY: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-29"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-30"), Some("2024-01-30"),
+        Some("2024-01-31"), Some("2024-01-31"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-01"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"),
+        Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), None,
+    ];
+    const ROUND_ROBIN: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-02"), Some("2024-02-03"),
+        Some("2024-02-04"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-02"),
+        Some("2024-02-03"), Some("2024-02-04"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-04"), Some("2024-01-29"),
+    ];
+    // 20 jobs, weekends and February weighted up: day weights 1,1,1,2,2,4,6 floor to 1,1,1,2,2,4,7
+    // and the two leftover jobs go to the largest remainders, Saturday then Thursday
+    const WEIGHTED_20: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-01"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"),
+        Some("2024-02-03"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"),
+        Some("2024-02-04"), Some("2024-02-04"), None, None,
+    ];
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn profile() -> DateProfile {
+        DateProfile { day_of_week: [1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 3.0], month: [1.0, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0] }
+    }
+
+    fn assert_allocates(allocator: &DateAllocator, expected: &[Option<&str>]) {
+        for (job_index, expected) in expected.iter().enumerate() {
+            let actual = allocator.date_for_job(job_index as i32).ok();
+            assert_eq!(actual, expected.map(date), "job {}", job_index);
+        }
+    }
+
+    #[test]
+    fn block_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::Block { start: date("2024-01-29"), end: date("2024-02-04"), jobs_per_day: 3 };
+        assert_allocates(&allocator, &BLOCK_3_PER_DAY);
+    }
+
+    #[test]
+    fn round_robin_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::RoundRobin { start: date("2024-01-29"), end: date("2024-02-04") };
+        assert_allocates(&allocator, &ROUND_ROBIN);
+    }
+
+    #[test]
+    fn weighted_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::weighted(date("2024-01-29"), date("2024-02-04"), 20, &profile()).unwrap();
+        assert_allocates(&allocator, &WEIGHTED_20);
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, Result};
+use arrow::record_batch::RecordBatch;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use log::{debug, info};
+use rand::{SeedableRng, seq::SliceRandom, rngs::StdRng};
+use rayon::prelude::*;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::data_generator::*;
+use crate::dynamodb_client::get_100_random_hash_pans;
+use crate::job_size::JobSize;
+use crate::logging::{self, Unit};
+use crate::metrics::TableRecord;
+use crate::storage::Storage;
+use crate::table_options::{TableOption, TableOptions};
+use crate::{generate_parquet_data, upload_to_respective_buckets};
+
+// Uploads in flight at once, each holding one encoded table
+const MAX_CONCURRENT_UPLOADS: usize = 8;
+
+/// Partition and settings shared by every thread of a job
+pub struct JobContext {
+    pub job_index: i32,
+    pub partition_job_order: i64,
+    pub job_size: JobSize,
+    pub process_date: String,
+    /// Fraction of transactions with a chargeback (0.001 = 0.1%)
+    pub chargeback_rate: f64,
+    pub tables: TableOptions,
+}
+
+/// Where a job's files go and what they are called
+pub struct Output {
+    pub storage: Storage,
+    pub payment_data_bucket: String,
+    pub authorization_bucket: String,
+    pub clearing_bucket: String,
+    pub chargeback_bucket: String,
+    pub file_stem: String,
+    pub year: i32,
+    pub month: String,
+    pub day: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Table {
+    Authorization,
+    AuthorizationHash,
+    Clearing,
+    ClearingHash,
+    Chargeback,
+    ChargebackHash,
+}
+
+impl Table {
+    const ALL: [Table; 6] = [
+        Table::Authorization,
+        Table::AuthorizationHash,
+        Table::Clearing,
+        Table::ClearingHash,
+        Table::Chargeback,
+        Table::ChargebackHash,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Table::Authorization => "authorization",
+            Table::AuthorizationHash => "authorization_hash",
+            Table::Clearing => "clearing",
+            Table::ClearingHash => "clearing_hash",
+            Table::Chargeback => "chargeback",
+            Table::ChargebackHash => "chargeback_hash",
+        }
+    }
+
+    fn option(self, tables: &TableOptions) -> TableOption {
+        match self {
+            Table::Authorization => tables.authorization,
+            Table::AuthorizationHash => tables.authorization_hash,
+            Table::Clearing => tables.clearing,
+            Table::ClearingHash => tables.clearing_hash,
+            Table::Chargeback => tables.chargeback,
+            Table::ChargebackHash => tables.chargeback_hash,
+        }
+    }
+
+    fn specialized_bucket(self, output: &Output) -> &str {
+        match self {
+            Table::Authorization | Table::AuthorizationHash => &output.authorization_bucket,
+            Table::Clearing | Table::ClearingHash => &output.clearing_bucket,
+            Table::Chargeback | Table::ChargebackHash => &output.chargeback_bucket,
+        }
+    }
+
+    fn generate(self, inputs: &ThreadInputs, context: &JobContext) -> RecordBatch {
+        let (rows, order, thread_id, size, date) = (context.job_size.rows_per_thread, context.partition_job_order, inputs.thread_id, context.job_size, context.process_date.as_str());
+        let (seeds, chargeback_seeds, pool) = (&inputs.row_seeds, &inputs.chargeback_row_seeds, &inputs.hash_pan_pool);
+        match self {
+            Table::Authorization => generate_authorization_batch(rows, seeds, order, thread_id, size, chargeback_seeds, pool, date),
+            Table::AuthorizationHash => generate_authorization_hash_batch(rows, seeds, order, thread_id, size, chargeback_seeds, pool, date),
+            Table::Clearing => generate_clearing_batch(rows, seeds, order, thread_id, size, chargeback_seeds, pool, date),
+            Table::ClearingHash => generate_clearing_hash_batch(rows, seeds, order, thread_id, size, chargeback_seeds, pool, date),
+            Table::Chargeback => generate_chargeback_batch(chargeback_seeds.len(), chargeback_seeds, order, thread_id, size, pool, date),
+            Table::ChargebackHash => generate_chargeback_hash_batch(chargeback_seeds.len(), chargeback_seeds, order, thread_id, size, pool, date),
+        }
+    }
+}
+
+/// Seeds and card pool of one thread; every table of the thread is generated from them
+struct ThreadInputs {
+    thread_id: i32,
+    row_seeds: Vec<u64>,
+    chargeback_row_seeds: Vec<u64>,
+    hash_pan_pool: Vec<String>,
+}
+
+impl ThreadInputs {
+    fn new(context: &JobContext, thread_id: i32, hash_pan_pool: Vec<String>) -> Self {
+        // Unique per job and thread, so no two threads anywhere share row seeds
+        let thread_seed = (context.job_index as u64) * 1000 + (thread_id as u64);
+        let row_seeds: Vec<u64> = (0..context.job_size.rows_per_thread).map(|row_idx| context.job_size.row_seed(thread_seed, row_idx)).collect();
+
+        // Chargebacks pick from the thread's authorization transactions
+        let total_chargebacks_needed = (context.job_size.rows_per_thread as f64 * context.chargeback_rate) as usize;
+        let chargeback_seed = (context.job_index as u64).wrapping_mul(1000).wrapping_add(thread_id as u64);
+        let mut rng = StdRng::seed_from_u64(chargeback_seed);
+        let chargeback_row_seeds = row_seeds.choose_multiple(&mut rng, total_chargebacks_needed).cloned().collect();
+
+        Self { thread_id, row_seeds, chargeback_row_seeds, hash_pan_pool }
+    }
+}
+
+/// One thread's table, encoded and waiting for its upload
+struct EncodedTable {
+    table: Table,
+    thread_id: i32,
+    data: Vec<u8>,
+    record: TableRecord,
+}
+
+/// Generate and upload every table of every thread of the job. Tables are generated and encoded
+/// on the rayon pool and handed through a bounded channel to uploads on this Tokio runtime, so
+/// the next table is generated while the previous ones upload. Returns a record of each table.
+pub async fn run_job(context: JobContext, output: Output, dynamodb_client: &DynamoDbClient, hash_pan_table_name: &str) -> Result<Vec<TableRecord>> {
+    let context = Arc::new(context);
+    let output = Arc::new(output);
+
+    let mut pool_requests = JoinSet::new();
+    for thread_id in 1..=context.job_size.threads {
+        let (dynamodb_client, hash_pan_table_name) = (dynamodb_client.clone(), hash_pan_table_name.to_string());
+        pool_requests.spawn(logging::scope(Unit { thread_id, table: None }, async move {
+            let hash_pan_pool = get_100_random_hash_pans(&dynamodb_client, &hash_pan_table_name, thread_id).await?;
+            info!("🔑 Thread {} retrieved {} hash_pans for random selection", thread_id, hash_pan_pool.len());
+            Ok::<_, anyhow::Error>((thread_id, hash_pan_pool))
+        }));
+    }
+    let mut pools = pool_requests.join_all().await.into_iter().collect::<Result<Vec<_>>>()?;
+    pools.sort_by_key(|(thread_id, _)| *thread_id);
+    let threads: Vec<ThreadInputs> = pools.into_iter().map(|(thread_id, pool)| ThreadInputs::new(&context, thread_id, pool)).collect();
+
+    // Work units are (thread, table); chargeback tables are skipped for threads without chargebacks
+    let units: Vec<(usize, Table)> = threads
+        .iter()
+        .enumerate()
+        .flat_map(|(index, inputs)| {
+            Table::ALL
+                .into_iter()
+                .filter(|table| table.option(&context.tables).enabled)
+                .filter(|table| !matches!(table, Table::Chargeback | Table::ChargebackHash) || !inputs.chargeback_row_seeds.is_empty())
+                .map(move |table| (index, table))
+        })
+        .collect();
+    info!("🧵 Job {} generating {} tables across {} threads", context.job_index, units.len(), threads.len());
+
+    // One encoded table per thread can wait for an upload slot before generation pauses
+    let (sender, mut receiver) = mpsc::channel::<EncodedTable>(context.job_size.threads as usize);
+    let generation = tokio::task::spawn_blocking({
+        let context = context.clone();
+        move || {
+            units.into_par_iter().try_for_each(|(index, table)| {
+                let inputs = &threads[index];
+                let _unit = logging::enter(Unit { thread_id: inputs.thread_id, table: Some(table.name()) });
+                let started = Instant::now();
+                let batch = table.generate(inputs, &context);
+                let generated = Instant::now();
+                let rows = batch.num_rows();
+                let data = generate_parquet_data(batch)?;
+                info!("✅ Thread {} completed {} table ({} rows, {} bytes)", inputs.thread_id, table.name(), rows, data.len());
+                let record = TableRecord {
+                    table: table.name(),
+                    rows: rows as u64,
+                    bytes: data.len() as u64,
+                    generate_seconds: (generated - started).as_secs_f64(),
+                    encode_seconds: generated.elapsed().as_secs_f64(),
+                    ..Default::default()
+                };
+                sender
+                    .blocking_send(EncodedTable { table, thread_id: inputs.thread_id, data, record })
+                    .map_err(|_| anyhow!("Uploads stopped before thread {} finished", inputs.thread_id))
+            })
+        }
+    });
+
+    let mut uploads = JoinSet::new();
+    let mut records = Vec::new();
+    while let Some(encoded) = receiver.recv().await {
+        if uploads.len() >= MAX_CONCURRENT_UPLOADS {
+            if let Some(finished) = uploads.join_next().await {
+                records.push(finished??);
+            }
+        }
+        let (output, tables) = (output.clone(), context.tables);
+        let unit = Unit { thread_id: encoded.thread_id, table: Some(encoded.table.name()) };
+        uploads.spawn(logging::scope(unit, async move {
+            let specialized_bucket = encoded.table.option(&tables).specialized_bucket.then(|| encoded.table.specialized_bucket(&output));
+            let table_name = encoded.table.name();
+            let started = Instant::now();
+            let retries = upload_to_respective_buckets(&output.storage, &output.payment_data_bucket, specialized_bucket, table_name, table_name, encoded.data, &output.file_stem, encoded.thread_id, output.year, &output.month, &output.day).await?;
+            debug!("Uploaded thread {} {} table", encoded.thread_id, table_name);
+            Ok::<_, anyhow::Error>(TableRecord {
+                files: 1 + specialized_bucket.is_some() as u64,
+                upload_seconds: started.elapsed().as_secs_f64(),
+                upload_retries: retries,
+                ..encoded.record
+            })
+        }));
+    }
+    while let Some(finished) = uploads.join_next().await {
+        records.push(finished??);
+    }
+    generation.await??;
+    Ok(records)
+}
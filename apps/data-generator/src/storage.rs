@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Result};
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client as S3Client;
+use log::{debug, error, warn};
+use std::path::PathBuf;
+
+/// Where generated files go: S3, or a local directory with one subdirectory per bucket
+#[derive(Clone)]
+pub enum Storage {
+    S3(S3Client),
+    Local(PathBuf),
+}
+
+impl Storage {
+    /// Write one object, returning how many failed attempts were retried
+    pub async fn put(&self, bucket: &str, key: &str, body: &[u8]) -> Result<u64> {
+        match self {
+            Storage::S3(s3_client) => upload_with_retry(s3_client, bucket, key, body, 3).await,
+            Storage::Local(dir) => {
+                let path = dir.join(bucket).join(key);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&path, body).await.map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+                debug!("Wrote {}", path.display());
+                Ok(0)
+            }
+        }
+    }
+}
+
+async fn upload_with_retry(s3_client: &S3Client, bucket: &str, key: &str, body: &[u8], max_retries: u32) -> Result<u64> {
+    for attempt in 0..max_retries {
+        let body_stream = ByteStream::from(body.to_vec());
+        match s3_client.put_object().bucket(bucket).key(key).body(body_stream).send().await {
+            Ok(_) => {
+                debug!("Upload successful: {} (attempt {})", key, attempt + 1);
+                return Ok(attempt as u64);
+            },
+            Err(e) => {
+                let error_details = if let Some(service_err) = e.as_service_error() {
+                    let error_code = service_err.code().unwrap_or("Unknown");
+                    let error_message = service_err.message().unwrap_or("No message");
+                    format!("Service error - Code: {}, Message: {}", error_code, error_message)
+                } else {
+                    format!("SDK error: {}", e)
+                };
+                
+                if attempt == max_retries - 1 {
+                    error!("Upload failed after {} attempts: {} - {}", max_retries, key, error_details);
+                    return Err(anyhow!("Failed to upload {} after {} attempts. Last error: {}", key, max_retries, error_details));
+                }
+                
+                let delay = std::time::Duration::from_millis(1000 * (2_u64.pow(attempt)));
+                warn!("Upload attempt {} failed for {}: {}. Retrying in {:?}...", attempt + 1, key, error_details, delay);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+    unreachable!()
+}
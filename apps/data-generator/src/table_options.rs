@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::env;
+
+/// Options for one output table, as set in the job submitter's run spec
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableOption {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Copy the table to its specialized bucket as well as the payment data bucket
+    #[serde(default = "default_true")]
+    pub specialized_bucket: bool,
+}
+
+impl Default for TableOption {
+    fn default() -> Self {
+        Self { enabled: true, specialized_bucket: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableOptions {
+    #[serde(default)]
+    pub authorization: TableOption,
+    #[serde(default)]
+    pub authorization_hash: TableOption,
+    #[serde(default)]
+    pub clearing: TableOption,
+    #[serde(default)]
+    pub clearing_hash: TableOption,
+    #[serde(default)]
+    pub chargeback: TableOption,
+    #[serde(default)]
+    pub chargeback_hash: TableOption,
+}
+
+impl TableOptions {
+    /// Parse TABLE_OPTIONS (JSON). Without it every table is generated and written to both buckets.
+    pub fn from_env() -> Result<Self> {
+        match env::var("TABLE_OPTIONS") {
+            Ok(value) => serde_json::from_str(&value).map_err(|e| anyhow!("Invalid TABLE_OPTIONS: {}", e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
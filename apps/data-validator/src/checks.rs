@@ -0,0 +1,246 @@
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, Decimal128Array, Int64Array, StringArray};
+use arrow::datatypes::DataType;
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::dataset::DataFile;
+
+// Same country → currency table the generator draws merchant and issuer countries from
+pub const COUNTRY_CURRENCIES: [(&str, &str); 43] = [
+    ("USA", "USD"), ("CAN", "CAD"), ("GBR", "GBP"), ("JPN", "JPY"), ("AUS", "AUD"), ("CHE", "CHF"),
+    ("SWE", "SEK"), ("NOR", "NOK"), ("DNK", "DKK"), ("POL", "PLN"), ("CZE", "CZK"), ("HUN", "HUF"),
+    ("BGR", "BGN"), ("ROU", "RON"), ("KOR", "KRW"), ("MEX", "MXN"), ("BRA", "BRL"), ("ARG", "ARS"),
+    ("CHL", "CLP"), ("COL", "COP"), ("PER", "PEN"), ("ARE", "AED"), ("ZAF", "ZAR"), ("SGP", "SGD"),
+    ("DEU", "EUR"), ("FRA", "EUR"), ("ITA", "EUR"), ("ESP", "EUR"), ("NLD", "EUR"), ("BEL", "EUR"),
+    ("AUT", "EUR"), ("IRL", "EUR"), ("PRT", "EUR"), ("GRC", "EUR"), ("FIN", "EUR"), ("SVN", "EUR"),
+    ("EST", "EUR"), ("LVA", "EUR"), ("LTU", "EUR"), ("LUX", "EUR"), ("MLT", "EUR"), ("CYP", "EUR"),
+    ("HRV", "EUR"),
+];
+
+// Currency columns and the country column whose currency they carry, checked where a table has both
+const CURRENCY_RULES: [(&str, &str); 8] = [
+    ("currency_code", "issuer_country_code"),
+    ("original_currency", "issuer_country_code"),
+    ("settlement_currency", "issuer_country_code"),
+    ("clearing_currency", "issuer_country_code"),
+    ("issuer_currency", "issuer_country_code"),
+    ("cardholder_currency", "issuer_country_code"),
+    ("billing_currency", "issuer_country_code"),
+    ("local_currency", "merchant_country_code"),
+];
+
+/// Tables whose sequence numbers the uniqueness and chargeback checks read
+const SEQUENCE_TABLES: [&str; 3] = ["authorization", "clearing", "chargeback"];
+
+/// Rows or values one check looked at, and the ones that broke it
+#[derive(Debug, Default)]
+pub struct Tally {
+    pub checked: u64,
+    pub violations: u64,
+    pub examples: Vec<String>,
+}
+
+impl Tally {
+    pub fn record(&mut self, ok: bool, max_examples: usize, example: impl FnOnce() -> String) {
+        self.checked += 1;
+        if !ok {
+            self.violations += 1;
+            if self.examples.len() < max_examples {
+                self.examples.push(example());
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: Tally, max_examples: usize) {
+        self.checked += other.checked;
+        self.violations += other.violations;
+        let room = max_examples.saturating_sub(self.examples.len());
+        self.examples.extend(other.examples.into_iter().take(room));
+    }
+}
+
+/// What one file contributes to the checks
+#[derive(Debug, Default)]
+pub struct FileScan {
+    pub rows: u64,
+    /// Non-null sequence numbers, for the tables in SEQUENCE_TABLES
+    pub sequence_numbers: Vec<i64>,
+    pub null_sequence_numbers: Tally,
+    pub cents: Tally,
+    pub currencies: Tally,
+}
+
+/// Read the columns the checks need from one Parquet file and check its rows
+pub fn scan_file(file: &DataFile, data: Bytes, max_examples: usize) -> Result<FileScan> {
+    let context = |e: &dyn std::fmt::Display| anyhow!("Failed to read {}: {}", file.key, e);
+    let builder = ParquetRecordBatchReaderBuilder::try_new(data).map_err(|e| context(&e))?;
+    let mut scan = FileScan { rows: builder.metadata().file_metadata().num_rows().max(0) as u64, ..Default::default() };
+
+    let schema = builder.schema().clone();
+    let has = |name: &str| schema.field_with_name(name).is_ok();
+    let read_sequence_numbers = SEQUENCE_TABLES.contains(&file.table.as_str()) && has("sequence_number");
+    // Every <name>_cents column with a decimal <name> column next to it
+    let cents_pairs: Vec<(String, String)> = schema
+        .fields()
+        .iter()
+        .filter_map(|field| {
+            let amount = field.name().strip_suffix("_cents")?;
+            let decimal = schema.field_with_name(amount).ok()?;
+            matches!(decimal.data_type(), DataType::Decimal128(_, _)).then(|| (amount.to_string(), field.name().clone()))
+        })
+        .collect();
+    let currency_rules: Vec<(&str, &str)> = CURRENCY_RULES.into_iter().filter(|(currency, country)| has(currency) && has(country)).collect();
+
+    let mut columns: Vec<&str> = Vec::new();
+    if read_sequence_numbers {
+        columns.push("sequence_number");
+    }
+    for (amount, cents) in &cents_pairs {
+        columns.extend([amount.as_str(), cents.as_str()]);
+    }
+    for (currency, country) in &currency_rules {
+        columns.extend([*currency, *country]);
+    }
+    // Hash tables only count towards row counts, which the footer already holds
+    if columns.is_empty() {
+        return Ok(scan);
+    }
+    columns.sort_unstable();
+    columns.dedup();
+
+    let mask = ProjectionMask::columns(builder.parquet_schema(), columns.iter().copied());
+    let reader = builder.with_projection(mask).build().map_err(|e| context(&e))?;
+    let currencies: HashMap<&str, &str> = COUNTRY_CURRENCIES.into_iter().collect();
+    let mut row_offset = 0;
+    for batch in reader {
+        let batch = batch.map_err(|e| context(&e))?;
+        let column = |name: &str| batch.column_by_name(name).ok_or_else(|| anyhow!("{} has no {} column", file.key, name));
+        let at = |row: usize| format!("{} {} row {}", file.date, file.key, row_offset + row);
+
+        if read_sequence_numbers {
+            let values = as_array::<Int64Array>(column("sequence_number")?, file, "sequence_number")?;
+            for row in 0..values.len() {
+                if values.is_null(row) {
+                    scan.null_sequence_numbers.record(false, max_examples, || format!("{}: sequence_number is null", at(row)));
+                } else {
+                    scan.sequence_numbers.push(values.value(row));
+                }
+            }
+        }
+
+        for (amount, cents) in &cents_pairs {
+            let amounts = as_array::<Decimal128Array>(column(amount)?, file, amount)?;
+            let cents_values = as_array::<Int64Array>(column(cents)?, file, cents)?;
+            let scale = amounts.scale() as u32;
+            for row in 0..amounts.len() {
+                let expected = (!amounts.is_null(row)).then(|| amount_in_cents(amounts.value(row), scale));
+                let actual = (!cents_values.is_null(row)).then(|| cents_values.value(row) as i128);
+                let matches = match (expected, actual) {
+                    (None, None) => true,
+                    (Some(expected), Some(actual)) => expected == Some(actual),
+                    _ => false,
+                };
+                scan.cents.record(matches, max_examples, || {
+                    format!("{}: {} is {} but {} is {}", at(row), amount, amounts.value_as_string(row), cents, describe(actual))
+                });
+            }
+        }
+
+        for (currency, country) in &currency_rules {
+            let currency_values = as_array::<StringArray>(column(currency)?, file, currency)?;
+            let country_values = as_array::<StringArray>(column(country)?, file, country)?;
+            for row in 0..currency_values.len() {
+                let country_code = (!country_values.is_null(row)).then(|| country_values.value(row));
+                let expected = country_code.and_then(|code| currencies.get(code).copied());
+                let actual = (!currency_values.is_null(row)).then(|| currency_values.value(row));
+                scan.currencies.record(expected.is_some() && expected == actual, max_examples, || {
+                    format!("{}: {} {} with {} {}", at(row), country, describe(country_code), currency, describe(actual))
+                });
+            }
+        }
+        row_offset += batch.num_rows();
+    }
+    Ok(scan)
+}
+
+pub fn as_array<'a, T: 'static>(array: &'a dyn Array, file: &DataFile, name: &str) -> Result<&'a T> {
+    array.as_any().downcast_ref::<T>().ok_or_else(|| anyhow!("{} column {} has unexpected type {}", file.key, name, array.data_type()))
+}
+
+/// A decimal amount in cents, or None when it has fractions of a cent
+fn amount_in_cents(unscaled: i128, scale: u32) -> Option<i128> {
+    if scale <= 2 {
+        Some(unscaled * 10i128.pow(2 - scale))
+    } else {
+        let factor = 10i128.pow(scale - 2);
+        (unscaled % factor == 0).then_some(unscaled / factor)
+    }
+}
+
+fn describe<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "null".to_string())
+}
+
+/// Count sequence numbers seen more than once; `sorted` must be sorted
+pub fn check_unique(table: &str, sorted: &[i64], date: &str, tally: &mut Tally, max_examples: usize) {
+    for (index, value) in sorted.iter().enumerate() {
+        let duplicate = index > 0 && sorted[index - 1] == *value;
+        tally.record(!duplicate, max_examples, || format!("{}: {} sequence_number {} appears more than once", date, table, value));
+    }
+}
+
+/// Count chargeback sequence numbers missing from a table's sorted sequence numbers
+pub fn check_subset(chargebacks: &[i64], table: &str, sorted: &[i64], date: &str, tally: &mut Tally, max_examples: usize) {
+    for value in chargebacks {
+        let found = sorted.binary_search(value).is_ok();
+        tally.record(found, max_examples, || format!("{}: chargeback sequence_number {} is not in {}", date, value, table));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+impl Status {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Status::Pass => "✅",
+            Status::Fail => "❌",
+            Status::Skipped => "⏭️",
+        }
+    }
+}
+
+/// Outcome of one check over the whole dataset
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub status: Status,
+    pub checked: u64,
+    pub violations: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+    /// Why a check was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl CheckResult {
+    pub fn from_tally(name: &'static str, description: &'static str, tally: Tally) -> Self {
+        let status = if tally.violations > 0 { Status::Fail } else { Status::Pass };
+        Self { name, description, status, checked: tally.checked, violations: tally.violations, examples: tally.examples, note: None }
+    }
+
+    pub fn skipped(name: &'static str, description: &'static str, note: impl Into<String>) -> Self {
+        Self { name, description, status: Status::Skipped, checked: 0, violations: 0, examples: Vec::new(), note: Some(note.into()) }
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, bail, Result};
+use aws_sdk_s3::Client as S3Client;
+use bytes::Bytes;
+use chrono::NaiveDate;
+use std::path::{Path, PathBuf};
+
+/// A generated file: `<table>/<yyyy>/<mm>/<dd>/<name>.parquet` under the dataset root
+#[derive(Debug, Clone)]
+pub struct DataFile {
+    pub table: String,
+    pub date: NaiveDate,
+    /// Relative to the dataset root
+    pub key: String,
+    pub name: String,
+}
+
+impl DataFile {
+    fn parse(key: &str) -> Option<Self> {
+        let parts: Vec<&str> = key.split('/').collect();
+        let [table, year, month, day, name] = parts[..] else {
+            return None;
+        };
+        if !name.ends_with(".parquet") {
+            return None;
+        }
+        let date = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+        Some(Self { table: table.to_string(), date, key: key.to_string(), name: name.to_string() })
+    }
+
+    /// Files are named `run_<run_id>_job_<n>_thread_<t>.parquet`, or `job_...` without a run ID.
+    /// The whole name is matched, as job-submitter purge does: a prefix would also take the files
+    /// of run `a_job_5` for run `a`.
+    pub fn belongs_to_run(&self, run_id: &str) -> bool {
+        let digits = |value: &str| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+        self.name
+            .strip_prefix("run_")
+            .and_then(|rest| rest.strip_prefix(run_id))
+            .and_then(|rest| rest.strip_prefix("_job_"))
+            .and_then(|rest| rest.strip_suffix(".parquet"))
+            .and_then(|rest| rest.split_once("_thread_"))
+            .is_some_and(|(job, thread)| digits(job) && digits(thread))
+    }
+}
+
+/// Where a dataset is: a bucket (and prefix) in S3, or a local directory laid out the same way
+pub enum Dataset {
+    S3 { client: S3Client, bucket: String, prefix: String },
+    Local(PathBuf),
+}
+
+impl Dataset {
+    /// `s3://bucket[/prefix]` or a local directory
+    pub async fn open(location: &str) -> Result<Self> {
+        match location.strip_prefix("s3://") {
+            Some(path) => {
+                let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+                if bucket.is_empty() {
+                    bail!("Invalid dataset {:?}: expected s3://bucket[/prefix]", location);
+                }
+                let prefix = match prefix.trim_end_matches('/') {
+                    "" => String::new(),
+                    prefix => format!("{}/", prefix),
+                };
+                Ok(Dataset::S3 { client: s3_client().await, bucket: bucket.to_string(), prefix })
+            }
+            None => {
+                let dir = PathBuf::from(location);
+                if !dir.is_dir() {
+                    bail!("Dataset directory {} does not exist", dir.display());
+                }
+                Ok(Dataset::Local(dir))
+            }
+        }
+    }
+
+    /// Every data file of a table
+    pub async fn list_table(&self, table: &str) -> Result<Vec<DataFile>> {
+        let keys = match self {
+            Dataset::S3 { client, bucket, prefix } => {
+                let mut keys = Vec::new();
+                let mut continuation_token = None;
+                let table_prefix = format!("{}{}/", prefix, table);
+                loop {
+                    let response = client
+                        .list_objects_v2()
+                        .bucket(bucket)
+                        .prefix(&table_prefix)
+                        .set_continuation_token(continuation_token)
+                        .send()
+                        .await
+                        .map_err(|e| anyhow!("Failed to list s3://{}/{}: {:?}", bucket, table_prefix, e))?;
+                    keys.extend(response.contents().iter().filter_map(|object| object.key()).map(|key| key[prefix.len()..].to_string()));
+                    continuation_token = response.next_continuation_token().map(str::to_string);
+                    if continuation_token.is_none() {
+                        break keys;
+                    }
+                }
+            }
+            Dataset::Local(dir) => {
+                let mut keys = Vec::new();
+                collect_local_keys(dir, &dir.join(table), &mut keys)?;
+                keys
+            }
+        };
+        Ok(keys.iter().filter_map(|key| DataFile::parse(key)).collect())
+    }
+
+    pub async fn read(&self, file: &DataFile) -> Result<Bytes> {
+        match self {
+            Dataset::S3 { client, bucket, prefix } => {
+                let key = format!("{}{}", prefix, file.key);
+                let response = client
+                    .get_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Failed to read s3://{}/{}: {:?}", bucket, key, e))?;
+                let body = response.body.collect().await.map_err(|e| anyhow!("Failed to read s3://{}/{}: {}", bucket, key, e))?;
+                Ok(body.into_bytes())
+            }
+            Dataset::Local(dir) => {
+                let path = dir.join(&file.key);
+                let content = tokio::fs::read(&path).await.map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+                Ok(Bytes::from(content))
+            }
+        }
+    }
+}
+
+// Only loaded for S3 locations, so local datasets need no AWS configuration
+async fn s3_client() -> S3Client {
+    S3Client::new(&aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await)
+}
+
+fn collect_local_keys(root: &Path, dir: &Path, keys: &mut Vec<String>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => bail!("Failed to list {}: {}", dir.display(), e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_local_keys(root, &path, keys)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            keys.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+/// Read a run manifest or other small file from a local path or `s3://bucket/key`
+pub async fn read_location(location: &str) -> Result<Vec<u8>> {
+    match location.strip_prefix("s3://").and_then(|path| path.split_once('/')) {
+        Some((bucket, key)) => {
+            let response = s3_client()
+                .await
+                .get_object()
+                .bucket(bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to read {}: {:?}", location, e))?;
+            let body = response.body.collect().await.map_err(|e| anyhow!("Failed to read {}: {}", location, e))?;
+            Ok(body.into_bytes().to_vec())
+        }
+        None => std::fs::read(location).map_err(|e| anyhow!("Failed to read {}: {}", location, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> DataFile {
+        DataFile::parse(&format!("authorization/2024/01/29/{}", name)).unwrap()
+    }
+
+    #[test]
+    fn belongs_to_run_matches_the_whole_file_name() {
+        assert!(file("run_a_job_1_thread_2.parquet").belongs_to_run("a"));
+        // Run a must not take the files of a run whose ID starts with "a_job_5"
+        assert!(!file("run_a_job_5_job_1_thread_2.parquet").belongs_to_run("a"));
+        assert!(file("run_a_job_5_job_1_thread_2.parquet").belongs_to_run("a_job_5"));
+        assert!(!file("run_nightly-2_job_1_thread_2.parquet").belongs_to_run("nightly"));
+        assert!(!file("job_1_thread_2.parquet").belongs_to_run("a"));
+    }
+}
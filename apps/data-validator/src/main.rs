@@ -0,0 +1,354 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+
+mod checks;
+mod dataset;
+mod manifest;
+mod profile;
+
+use checks::{CheckResult, FileScan, Status, Tally};
+use dataset::{DataFile, Dataset};
+use manifest::RunManifest;
+use profile::{ChargebackTarget, Counts, ProfileReport};
+
+const TABLES: [&str; 6] = ["authorization", "authorization_hash", "clearing", "clearing_hash", "chargeback", "chargeback_hash"];
+// Files read and scanned at once; each is held in memory whole while it is scanned
+const CONCURRENT_FILES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProfileFormat {
+    Markdown,
+    Json,
+}
+
+/// Check generated Parquet files against the invariants the generator guarantees and report
+/// every violation. Exits non-zero if any check fails, so it can run as a run's validate step.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    check: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Report the observed decline rate, transaction type mix, cross-border share, amounts,
+    /// chargeback rate and reason codes against the rates the generator aims for
+    Profile {
+        #[command(flatten)]
+        selection: Selection,
+        #[arg(long, value_enum, default_value_t = ProfileFormat::Markdown)]
+        output: ProfileFormat,
+    },
+}
+
+// Without a subcommand these flags run the checks
+#[derive(clap::Args, Debug)]
+struct Args {
+    #[command(flatten)]
+    selection: Selection,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Violations listed per check
+    #[arg(long, default_value_t = 10)]
+    max_examples: usize,
+}
+
+/// The files to read: a dataset, narrowed to one run and some days
+#[derive(clap::Args, Debug)]
+struct Selection {
+    /// Payment data bucket to read, as s3://bucket[/prefix] or a directory, holding
+    /// <table>/yyyy/mm/dd/*.parquet. Defaults to OUTPUT_DIR/PAYMENT_DATA_BUCKET_NAME when
+    /// OUTPUT_DIR is set, as in a --local run, and to s3://PAYMENT_DATA_BUCKET_NAME otherwise.
+    #[arg(long)]
+    dataset: Option<String>,
+    /// Only read files of this run; defaults to the manifest's run, then RUN_ID
+    #[arg(long)]
+    run_id: Option<String>,
+    /// Run manifest, as a path or s3://bucket/key. The checks compare row counts with it, and
+    /// profile takes its chargeback target from it.
+    #[arg(long, conflicts_with = "ledger")]
+    manifest: Option<String>,
+    /// Run ledger the manifest is read from (<ledger>/<run_id>.json), as passed to job-submitter
+    #[arg(long)]
+    ledger: Option<String>,
+    /// Only read these days (YYYY-MM-DD, comma-separated or repeated); the checks then skip row counts
+    #[arg(long = "date", value_delimiter = ',')]
+    dates: Vec<NaiveDate>,
+}
+
+/// The selected files by day, with where they came from
+struct Selected {
+    location: String,
+    run_id: Option<String>,
+    manifest: Option<RunManifest>,
+    dataset: Dataset,
+    by_date: BTreeMap<NaiveDate, Vec<DataFile>>,
+    files: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    dataset: String,
+    run_id: Option<String>,
+    dates: usize,
+    files: usize,
+    rows: BTreeMap<&'static str, u64>,
+    checks: Vec<CheckResult>,
+    passed: bool,
+}
+
+/// Totals of every check over the dates scanned so far
+#[derive(Default)]
+struct Tallies {
+    unique: Tally,
+    subset: Tally,
+    cents: Tally,
+    currencies: Tally,
+    rows: BTreeMap<&'static str, u64>,
+}
+
+fn default_dataset() -> Result<String> {
+    let bucket = env::var("PAYMENT_DATA_BUCKET_NAME").map_err(|_| anyhow!("Pass --dataset or set PAYMENT_DATA_BUCKET_NAME"))?;
+    Ok(match env::var("OUTPUT_DIR") {
+        Ok(output_dir) => format!("{}/{}", output_dir.trim_end_matches('/'), bucket),
+        Err(_) => format!("s3://{}", bucket),
+    })
+}
+
+async fn load_manifest(selection: &Selection, run_id: Option<&str>) -> Result<Option<RunManifest>> {
+    let location = match (&selection.manifest, &selection.ledger) {
+        (Some(manifest), _) => manifest.clone(),
+        (None, Some(ledger)) => {
+            let run_id = run_id.ok_or_else(|| anyhow!("--ledger needs --run-id or RUN_ID"))?;
+            format!("{}/{}.json", ledger.trim_end_matches('/'), run_id)
+        }
+        (None, None) => return Ok(None),
+    };
+    let content = dataset::read_location(&location).await?;
+    Ok(Some(RunManifest::parse(&content, &location)?))
+}
+
+/// Scan every file of one day and run the checks that compare tables within the day.
+/// Sequence numbers restart at every partition date, so that is as far as they need to be unique.
+async fn check_date(dataset: &Dataset, date: NaiveDate, files: Vec<DataFile>, tallies: &mut Tallies, max_examples: usize) -> Result<()> {
+    let scans: Vec<(DataFile, FileScan)> = stream::iter(files)
+        .map(|file| async move {
+            let data = dataset.read(&file).await?;
+            let scanned = file.clone();
+            let scan = tokio::task::spawn_blocking(move || checks::scan_file(&scanned, data, max_examples)).await??;
+            Ok::<_, anyhow::Error>((file, scan))
+        })
+        .buffer_unordered(CONCURRENT_FILES)
+        .try_collect()
+        .await?;
+
+    let mut sequence_numbers: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for (file, scan) in scans {
+        if let Some(table) = TABLES.iter().find(|table| **table == file.table) {
+            *tallies.rows.entry(table).or_default() += scan.rows;
+        }
+        sequence_numbers.entry(file.table).or_default().extend(scan.sequence_numbers);
+        tallies.unique.merge(scan.null_sequence_numbers, max_examples);
+        tallies.cents.merge(scan.cents, max_examples);
+        tallies.currencies.merge(scan.currencies, max_examples);
+    }
+
+    let day = date.to_string();
+    let chargebacks = sequence_numbers.remove("chargeback").unwrap_or_default();
+    for table in ["authorization", "clearing"] {
+        let Some(mut sorted) = sequence_numbers.remove(table) else {
+            continue;
+        };
+        sorted.sort_unstable();
+        if table == "authorization" {
+            checks::check_unique(table, &sorted, &day, &mut tallies.unique, max_examples);
+        }
+        checks::check_subset(&chargebacks, table, &sorted, &day, &mut tallies.subset, max_examples);
+    }
+    Ok(())
+}
+
+/// Resolve the dataset, run and manifest of a selection and list its files of `tables`
+async fn select(selection: &Selection, tables: &[&str]) -> Result<Selected> {
+    let location = match &selection.dataset {
+        Some(dataset) => dataset.clone(),
+        None => default_dataset()?,
+    };
+    let explicit_run_id = selection.run_id.clone().or_else(|| env::var("RUN_ID").ok());
+    let manifest = load_manifest(selection, explicit_run_id.as_deref()).await?;
+    let run_id = selection.run_id.clone().or_else(|| manifest.as_ref().map(|manifest| manifest.run_id.clone())).or(explicit_run_id);
+    if let (Some(run_id), Some(manifest)) = (&run_id, &manifest) {
+        if *run_id != manifest.run_id {
+            bail!("--run-id {} does not match the manifest's run {}", run_id, manifest.run_id);
+        }
+    }
+
+    let dataset = Dataset::open(&location).await?;
+    eprintln!("🔍 Listing {}{}", location, run_id.as_ref().map(|run_id| format!(" for run {}", run_id)).unwrap_or_default());
+    let mut by_date: BTreeMap<NaiveDate, Vec<DataFile>> = BTreeMap::new();
+    for table in tables {
+        for file in dataset.list_table(table).await? {
+            let in_run = run_id.as_ref().map(|run_id| file.belongs_to_run(run_id)).unwrap_or(true);
+            let in_dates = selection.dates.is_empty() || selection.dates.contains(&file.date);
+            if in_run && in_dates {
+                by_date.entry(file.date).or_default().push(file);
+            }
+        }
+    }
+    let files = by_date.values().map(Vec::len).sum();
+    if files == 0 {
+        bail!("No data files found in {}{}", location, run_id.as_ref().map(|run_id| format!(" for run {}", run_id)).unwrap_or_default());
+    }
+    Ok(Selected { location, run_id, manifest, dataset, by_date, files })
+}
+
+fn row_count_check(manifest: Option<&RunManifest>, args: &Args, rows: &BTreeMap<&'static str, u64>) -> CheckResult {
+    const NAME: &str = "row_counts";
+    const DESCRIPTION: &str = "Rows per table match the run manifest";
+    let Some(manifest) = manifest else {
+        return CheckResult::skipped(NAME, DESCRIPTION, "no --manifest or --ledger given");
+    };
+    if !args.selection.dates.is_empty() {
+        return CheckResult::skipped(NAME, DESCRIPTION, "--date limits the check to some days");
+    }
+    let mut tally = Tally::default();
+    for table in TABLES {
+        let found = rows.get(table).copied().unwrap_or(0);
+        let expected = manifest.expected_rows(table).unwrap_or(0).max(0) as u64;
+        tally.record(found == expected, args.max_examples, || format!("{}: {} rows, manifest expects {}", table, found, expected));
+    }
+    CheckResult::from_tally(NAME, DESCRIPTION, tally)
+}
+
+fn print_report(report: &Report) {
+    println!("📋 Validation of {}{}", report.dataset, report.run_id.as_ref().map(|run_id| format!(" (run {})", run_id)).unwrap_or_default());
+    println!("   {} files over {} days", report.files, report.dates);
+    for (table, rows) in &report.rows {
+        println!("   {:<20} {:>15} rows", table, rows);
+    }
+    for check in &report.checks {
+        match check.status {
+            Status::Skipped => println!("{} {}: skipped, {}", check.status.icon(), check.name, check.note.as_deref().unwrap_or("")),
+            _ => println!("{} {}: {} checked, {} violations ({})", check.status.icon(), check.name, check.checked, check.violations, check.description),
+        }
+        for example in &check.examples {
+            println!("      {}", example);
+        }
+    }
+    if report.passed {
+        println!("🎉 All checks passed");
+    }
+}
+
+/// Scan the transaction and chargeback files and compare their distributions with the generator's targets
+async fn run_profile(selection: Selection, output: ProfileFormat) -> Result<()> {
+    let selected = select(&selection, &["authorization", "clearing", "chargeback"]).await?;
+    let has_table = |table: &str| selected.by_date.values().flatten().any(|file| file.table == table);
+    // Authorization carries every profiled column; clearing stands in when it is disabled
+    let source = match ["authorization", "clearing"].into_iter().find(|table| has_table(table)) {
+        Some(source) => source,
+        None => bail!("Profiling needs authorization or clearing files in {}", selected.location),
+    };
+    let has_chargebacks = has_table("chargeback");
+    let dates = selected.by_date.len();
+    let files: Vec<DataFile> = selected.by_date.into_values().flatten().filter(|file| file.table == source || file.table == "chargeback").collect();
+    let total = files.len();
+    eprintln!("📂 Profiling {} {} and chargeback files", total, source);
+
+    let dataset = &selected.dataset;
+    let mut scans = stream::iter(files)
+        .map(|file| async move {
+            let data = dataset.read(&file).await?;
+            tokio::task::spawn_blocking(move || profile::scan_file(&file, data, source)).await?
+        })
+        .buffer_unordered(CONCURRENT_FILES);
+    let mut counts = Counts::default();
+    let mut scanned = 0;
+    while let Some(scan) = scans.next().await {
+        counts.merge(scan?);
+        scanned += 1;
+        if scanned % 100 == 0 || scanned == total {
+            eprintln!("   [{}/{}] files", scanned, total);
+        }
+    }
+
+    let chargeback_target = ChargebackTarget::resolve(selected.manifest.as_ref().map(|manifest| manifest.config.spec.chargeback_percentage));
+    let sections = profile::sections(&counts, source, has_chargebacks, &chargeback_target);
+    let off_target = sections.iter().flat_map(|section| &section.shares).filter(|share| share.on_target == Some(false)).count();
+    let report = ProfileReport {
+        dataset: selected.location,
+        run_id: selected.run_id,
+        dates,
+        files: total,
+        table: source,
+        tolerance_standard_errors: profile::TOLERANCE_STANDARD_ERRORS,
+        off_target,
+        sections,
+    };
+    match output {
+        ProfileFormat::Markdown => print!("{}", report.markdown()),
+        ProfileFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let args = match cli.command {
+        Some(Command::Profile { selection, output }) => return run_profile(selection, output).await,
+        None => cli.check,
+    };
+    let Selected { location, run_id, manifest, dataset, by_date, files } = select(&args.selection, &TABLES).await?;
+    let dates = by_date.len();
+    eprintln!("📂 Checking {} files over {} days", files, dates);
+
+    let mut tallies = Tallies::default();
+    for (checked, (date, day_files)) in by_date.into_iter().enumerate() {
+        check_date(&dataset, date, day_files, &mut tallies, args.max_examples).await?;
+        eprintln!("   [{}/{}] {}", checked + 1, dates, date);
+    }
+
+    let has_table = |table: &str| tallies.rows.contains_key(table);
+    let mut checks = vec![
+        if has_table("authorization") {
+            CheckResult::from_tally("unique_sequence_numbers", "authorization sequence_number is unique within each partition date", tallies.unique)
+        } else {
+            CheckResult::skipped("unique_sequence_numbers", "authorization sequence_number is unique within each partition date", "no authorization files")
+        },
+        if has_table("chargeback") && (has_table("authorization") || has_table("clearing")) {
+            CheckResult::from_tally("chargeback_sequence_numbers", "Every chargeback sequence_number is in authorization and clearing on its date", tallies.subset)
+        } else {
+            CheckResult::skipped("chargeback_sequence_numbers", "Every chargeback sequence_number is in authorization and clearing on its date", "needs chargeback and authorization or clearing files")
+        },
+        CheckResult::from_tally("cents_match_amounts", "Every <name>_cents column equals its decimal <name> column × 100", tallies.cents),
+        CheckResult::from_tally("currencies_match_countries", "Issuer and merchant currencies are those of their country codes", tallies.currencies),
+    ];
+    checks.push(row_count_check(manifest.as_ref(), &args, &tallies.rows));
+
+    let failed: Vec<&str> = checks.iter().filter(|check| check.status == Status::Fail).map(|check| check.name).collect();
+    let report = Report { dataset: location, run_id, dates, files, rows: tallies.rows, passed: failed.is_empty(), checks };
+    match args.output {
+        OutputFormat::Text => print_report(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    if !failed.is_empty() {
+        bail!("Validation failed: {}", failed.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+/// The parts of a job-submitter run manifest the row count check needs
+#[derive(Debug, Deserialize)]
+pub struct RunManifest {
+    pub run_id: String,
+    pub total_jobs: i64,
+    pub config: RunConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunConfig {
+    pub threads_per_job: i64,
+    pub spec: RunSpec,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunSpec {
+    pub rows_per_thread: i64,
+    pub chargeback_percentage: f64,
+    #[serde(default)]
+    pub tables: TableSpecs,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TableSpecs {
+    #[serde(default)]
+    pub authorization: TableSpec,
+    #[serde(default)]
+    pub authorization_hash: TableSpec,
+    #[serde(default)]
+    pub clearing: TableSpec,
+    #[serde(default)]
+    pub clearing_hash: TableSpec,
+    #[serde(default)]
+    pub chargeback: TableSpec,
+    #[serde(default)]
+    pub chargeback_hash: TableSpec,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TableSpec {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for TableSpec {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RunManifest {
+    pub fn parse(content: &[u8], location: &str) -> Result<Self> {
+        serde_json::from_slice(content).map_err(|e| anyhow!("Invalid run manifest {}: {}", location, e))
+    }
+
+    /// Rows a table of the run should hold, or None for a disabled table. Every generator thread
+    /// writes `rows_per_thread` rows and picks its chargebacks from them at the run's rate.
+    pub fn expected_rows(&self, table: &str) -> Option<i64> {
+        let spec = &self.config.spec;
+        let (enabled, rows_per_thread) = match table {
+            "authorization" => (spec.tables.authorization.enabled, spec.rows_per_thread),
+            "authorization_hash" => (spec.tables.authorization_hash.enabled, spec.rows_per_thread),
+            "clearing" => (spec.tables.clearing.enabled, spec.rows_per_thread),
+            "clearing_hash" => (spec.tables.clearing_hash.enabled, spec.rows_per_thread),
+            // Same arithmetic as the generator, so the truncation matches
+            "chargeback" => (spec.tables.chargeback.enabled, self.chargebacks_per_thread()),
+            "chargeback_hash" => (spec.tables.chargeback_hash.enabled, self.chargebacks_per_thread()),
+            _ => return None,
+        };
+        enabled.then_some(self.total_jobs * self.config.threads_per_job * rows_per_thread)
+    }
+
+    fn chargebacks_per_thread(&self) -> i64 {
+        let rate = self.config.spec.chargeback_percentage / 100.0;
+        (self.config.spec.rows_per_thread as usize as f64 * rate) as usize as i64
+    }
+}
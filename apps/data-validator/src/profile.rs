@@ -0,0 +1,371 @@
+use anyhow::{anyhow, Result};
+use arrow::array::{Int16Array, Int64Array, StringArray};
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::checks::{as_array, COUNTRY_CURRENCIES};
+use crate::dataset::DataFile;
+
+// Rates the generator draws every row with, in generate_transaction_business_logic
+const DECLINE_RATE: f64 = 0.05;
+const REVERSAL_RATE: f64 = 0.005;
+const TRANSACTION_TYPES: [(&str, f64); 6] = [
+    ("PURCHASE", 0.85),
+    ("REFUND", 0.03),
+    ("CASH_ADVANCE", 0.05),
+    ("BALANCE_TRANSFER", 0.04),
+    ("FEE", 0.02),
+    ("ADJUSTMENT", 0.01),
+];
+// Transaction amounts are drawn uniformly from this range
+const AMOUNT_RANGE: (f64, f64) = (10.00, 9999.99);
+// Chargebacks per row when neither the manifest nor CHARGEBACK_PERCENTAGE sets it
+const DEFAULT_CHARGEBACK_PERCENTAGE: f64 = 0.1;
+// The five reason codes each network's chargebacks draw from uniformly
+const REASON_CODES: [(&str, [&str; 5]); 7] = [
+    ("MASTERCARD", ["4855", "4834", "4837", "4863", "4871"]),
+    ("VISA", ["10.4", "11.1", "12.1", "13.1", "13.2"]),
+    ("AMEX", ["C02", "C08", "C14", "C18", "C28"]),
+    ("DISCOVER", ["4554", "4553", "4552", "4550", "4541"]),
+    ("JCB", ["J40", "J41", "J42", "J43", "J44"]),
+    ("DINERS", ["D10", "D11", "D12", "D13", "D14"]),
+    ("UNIONPAY", ["UP01", "UP02", "UP03", "UP04", "UP05"]),
+];
+
+const DECLINED_STATUS: i16 = 5;
+const REVERSED_STATUS: i16 = 3;
+/// Upper bounds of the amount histogram buckets in cents; the last bucket is open-ended
+const AMOUNT_BUCKET_BOUNDS: [i64; 8] = [1_000, 10_000, 50_000, 100_000, 250_000, 500_000, 750_000, 1_000_000];
+/// A share is off target when it is further than this many standard errors from the target
+pub const TOLERANCE_STANDARD_ERRORS: f64 = 4.0;
+
+/// Tallies of the profiled columns, summed over files
+#[derive(Debug, Default)]
+pub struct Counts {
+    pub transactions: u64,
+    statuses: u64,
+    declined: u64,
+    reversed: u64,
+    types: BTreeMap<String, u64>,
+    country_pairs: u64,
+    cross_border: u64,
+    amounts: u64,
+    amount_buckets: [u64; AMOUNT_BUCKET_BOUNDS.len() + 1],
+    pub chargebacks: u64,
+    reason_codes: BTreeMap<String, u64>,
+}
+
+impl Counts {
+    pub fn merge(&mut self, other: Counts) {
+        self.transactions += other.transactions;
+        self.statuses += other.statuses;
+        self.declined += other.declined;
+        self.reversed += other.reversed;
+        for (transaction_type, count) in other.types {
+            *self.types.entry(transaction_type).or_default() += count;
+        }
+        self.country_pairs += other.country_pairs;
+        self.cross_border += other.cross_border;
+        self.amounts += other.amounts;
+        for (bucket, count) in self.amount_buckets.iter_mut().zip(other.amount_buckets) {
+            *bucket += count;
+        }
+        self.chargebacks += other.chargebacks;
+        for (code, count) in other.reason_codes {
+            *self.reason_codes.entry(code).or_default() += count;
+        }
+    }
+}
+
+/// Count the profiled columns of one file: transaction columns of `source` files, reason codes of
+/// chargeback files. Columns a table lacks are left out of their section.
+pub fn scan_file(file: &DataFile, data: Bytes, source: &str) -> Result<Counts> {
+    let context = |e: &dyn std::fmt::Display| anyhow!("Failed to read {}: {}", file.key, e);
+    let builder = ParquetRecordBatchReaderBuilder::try_new(data).map_err(|e| context(&e))?;
+    let rows = builder.metadata().file_metadata().num_rows().max(0) as u64;
+    let mut counts = Counts::default();
+    let wanted: &[&str] = if file.table == source {
+        counts.transactions = rows;
+        &["transaction_status_code", "transaction_type", "merchant_country_code", "issuer_country_code", "transaction_amount_cents"]
+    } else if file.table == "chargeback" {
+        counts.chargebacks = rows;
+        &["reason_code"]
+    } else {
+        return Ok(counts);
+    };
+
+    let schema = builder.schema().clone();
+    let columns: Vec<&str> = wanted.iter().copied().filter(|name| schema.field_with_name(name).is_ok()).collect();
+    if columns.is_empty() {
+        return Ok(counts);
+    }
+    let mask = ProjectionMask::columns(builder.parquet_schema(), columns.iter().copied());
+    let reader = builder.with_projection(mask).build().map_err(|e| context(&e))?;
+    for batch in reader {
+        let batch = batch.map_err(|e| context(&e))?;
+        let column = |name: &str| batch.column_by_name(name).map(|array| array.as_ref());
+
+        if let Some(array) = column("transaction_status_code") {
+            let statuses = as_array::<Int16Array>(array, file, "transaction_status_code")?;
+            for status in statuses.iter().flatten() {
+                counts.statuses += 1;
+                counts.declined += (status == DECLINED_STATUS) as u64;
+                counts.reversed += (status == REVERSED_STATUS) as u64;
+            }
+        }
+        if let Some(array) = column("transaction_type") {
+            for transaction_type in as_array::<StringArray>(array, file, "transaction_type")?.iter().flatten() {
+                *counts.types.entry(transaction_type.to_string()).or_default() += 1;
+            }
+        }
+        if let (Some(merchant), Some(issuer)) = (column("merchant_country_code"), column("issuer_country_code")) {
+            let merchant = as_array::<StringArray>(merchant, file, "merchant_country_code")?;
+            let issuer = as_array::<StringArray>(issuer, file, "issuer_country_code")?;
+            for (merchant, issuer) in merchant.iter().zip(issuer.iter()) {
+                if let (Some(merchant), Some(issuer)) = (merchant, issuer) {
+                    counts.country_pairs += 1;
+                    counts.cross_border += (merchant != issuer) as u64;
+                }
+            }
+        }
+        if let Some(array) = column("transaction_amount_cents") {
+            for cents in as_array::<Int64Array>(array, file, "transaction_amount_cents")?.iter().flatten() {
+                counts.amounts += 1;
+                counts.amount_buckets[AMOUNT_BUCKET_BOUNDS.partition_point(|bound| *bound <= cents)] += 1;
+            }
+        }
+        if let Some(array) = column("reason_code") {
+            for code in as_array::<StringArray>(array, file, "reason_code")?.iter().flatten() {
+                *counts.reason_codes.entry(code.to_string()).or_default() += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// One observed share and the share the generator aims for
+#[derive(Debug, Serialize)]
+pub struct Share {
+    pub label: String,
+    pub count: u64,
+    pub observed: f64,
+    pub target: Option<f64>,
+    /// Within TOLERANCE_STANDARD_ERRORS of the target
+    pub on_target: Option<bool>,
+}
+
+impl Share {
+    fn new(label: impl Into<String>, count: u64, population: u64, target: Option<f64>) -> Self {
+        let observed = if population == 0 { 0.0 } else { count as f64 / population as f64 };
+        // Nothing to judge without rows
+        let on_target = target.filter(|_| population > 0).map(|target| {
+            let standard_error = (target * (1.0 - target) / population as f64).sqrt();
+            (observed - target).abs() <= (TOLERANCE_STANDARD_ERRORS * standard_error).max(1e-12)
+        });
+        Self { label: label.into(), count, observed, target, on_target }
+    }
+}
+
+/// One distribution: the rows it was measured over and how they split
+#[derive(Debug, Serialize)]
+pub struct Section {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: String,
+    pub population: u64,
+    pub shares: Vec<Share>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Section {
+    fn new(name: &'static str, title: &'static str, description: impl Into<String>, population: u64, shares: Vec<Share>) -> Self {
+        Self { name, title, description: description.into(), population, shares, note: None }
+    }
+
+    fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// Where the chargeback rate target came from, for the report
+pub struct ChargebackTarget {
+    pub percentage: f64,
+    pub source: &'static str,
+}
+
+impl ChargebackTarget {
+    pub fn resolve(manifest_percentage: Option<f64>) -> Self {
+        if let Some(percentage) = manifest_percentage {
+            return Self { percentage, source: "the run manifest" };
+        }
+        match std::env::var("CHARGEBACK_PERCENTAGE").ok().and_then(|value| value.parse().ok()) {
+            Some(percentage) => Self { percentage, source: "CHARGEBACK_PERCENTAGE" },
+            None => Self { percentage: DEFAULT_CHARGEBACK_PERCENTAGE, source: "the generator default" },
+        }
+    }
+}
+
+/// Turn the counts into the report's sections. `source` is the table the transaction
+/// distributions were read from; `has_chargebacks` is false when no chargeback files were found.
+pub fn sections(counts: &Counts, source: &str, has_chargebacks: bool, chargeback_target: &ChargebackTarget) -> Vec<Section> {
+    let mut sections = vec![
+        Section::new(
+            "decline_rate",
+            "Decline rate",
+            format!("Rows of {} with transaction_status_code {} (declined)", source, DECLINED_STATUS),
+            counts.statuses,
+            vec![Share::new("Declined", counts.declined, counts.statuses, Some(DECLINE_RATE))],
+        ),
+        Section::new(
+            "reversal_rate",
+            "Reversal rate",
+            format!("Approved rows of {} with transaction_status_code {} (reversed)", source, REVERSED_STATUS),
+            counts.statuses - counts.declined,
+            vec![Share::new("Reversed", counts.reversed, counts.statuses - counts.declined, Some(REVERSAL_RATE))],
+        ),
+    ];
+
+    let types = counts.types.values().sum();
+    let mut type_shares: Vec<Share> =
+        TRANSACTION_TYPES.iter().map(|(name, target)| Share::new(*name, counts.types.get(*name).copied().unwrap_or(0), types, Some(*target))).collect();
+    // Types the generator never draws are shown against a zero target
+    type_shares.extend(counts.types.iter().filter(|(name, _)| !TRANSACTION_TYPES.iter().any(|(known, _)| known == name)).map(|(name, count)| Share::new(name.clone(), *count, types, Some(0.0))));
+    sections.push(Section::new("transaction_type_mix", "Transaction type mix", format!("Rows of {} by transaction_type", source), types, type_shares));
+
+    // Merchant and issuer countries are drawn independently and uniformly
+    let cross_border_target = 1.0 - 1.0 / COUNTRY_CURRENCIES.len() as f64;
+    sections.push(Section::new(
+        "cross_border_share",
+        "Cross-border share",
+        format!("Rows of {} whose merchant_country_code differs from issuer_country_code", source),
+        counts.country_pairs,
+        vec![Share::new("Cross-border", counts.cross_border, counts.country_pairs, Some(cross_border_target))],
+    ));
+
+    let (low, high) = AMOUNT_RANGE;
+    let amount_shares = counts
+        .amount_buckets
+        .iter()
+        .enumerate()
+        .map(|(bucket, count)| {
+            let lower = bucket.checked_sub(1).map(|index| AMOUNT_BUCKET_BOUNDS[index] as f64 / 100.0);
+            let upper = AMOUNT_BUCKET_BOUNDS.get(bucket).map(|bound| *bound as f64 / 100.0);
+            let overlap = (upper.unwrap_or(f64::MAX).min(high) - lower.unwrap_or(0.0).max(low)).max(0.0);
+            let label = match (lower, upper) {
+                (None, Some(upper)) => format!("< {}", upper),
+                (Some(lower), Some(upper)) => format!("{} – {}", lower, upper),
+                (Some(lower), None) => format!("≥ {}", lower),
+                (None, None) => unreachable!(),
+            };
+            Share::new(label, *count, counts.amounts, Some(overlap / (high - low)))
+        })
+        .collect();
+    sections.push(Section::new(
+        "amount_histogram",
+        "Transaction amounts",
+        format!("Rows of {} by transaction_amount_cents / 100, drawn uniformly from {} to {}", source, low, high),
+        counts.amounts,
+        amount_shares,
+    ));
+
+    let chargeback_rate = Section::new(
+        "chargeback_rate",
+        "Chargeback rate",
+        format!("Chargeback rows per row of {}", source),
+        counts.transactions,
+        Vec::new(),
+    );
+    let reason_codes = Section::new("reason_code_mix", "Chargeback reason codes", "Chargeback rows by reason_code", counts.reason_codes.values().sum(), Vec::new());
+    if !has_chargebacks {
+        sections.push(chargeback_rate.with_note("No chargeback files"));
+        sections.push(reason_codes.with_note("No chargeback files"));
+        return sections;
+    }
+    let target = chargeback_target.percentage / 100.0;
+    sections.push(Section {
+        shares: vec![Share::new("Chargebacks", counts.chargebacks, counts.transactions, Some(target))],
+        ..chargeback_rate.with_note(format!("Target {}% from {}", chargeback_target.percentage, chargeback_target.source))
+    });
+
+    // A code's target is a fifth of its network's chargebacks, so brand mixes profile correctly
+    let population = reason_codes.population;
+    let network_of = |code: &str| REASON_CODES.iter().find(|(_, codes)| codes.contains(&code)).map(|(network, _)| *network);
+    let mut network_counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for (code, count) in &counts.reason_codes {
+        *network_counts.entry(network_of(code).unwrap_or("UNKNOWN")).or_default() += count;
+    }
+    let mut code_shares = Vec::new();
+    for (network, codes) in REASON_CODES.iter().filter(|(network, _)| network_counts.contains_key(network)) {
+        let network_share = network_counts[network] as f64 / population as f64;
+        for code in codes {
+            let count = counts.reason_codes.get(*code).copied().unwrap_or(0);
+            code_shares.push(Share::new(format!("{} {}", network, code), count, population, Some(network_share / codes.len() as f64)));
+        }
+    }
+    code_shares.extend(counts.reason_codes.iter().filter(|(code, _)| network_of(code).is_none()).map(|(code, count)| Share::new(code.clone(), *count, population, Some(0.0))));
+    sections.push(Section { shares: code_shares, ..reason_codes });
+    sections
+}
+
+/// The whole profile, as printed
+#[derive(Debug, Serialize)]
+pub struct ProfileReport {
+    pub dataset: String,
+    pub run_id: Option<String>,
+    pub dates: usize,
+    pub files: usize,
+    pub table: &'static str,
+    pub tolerance_standard_errors: f64,
+    pub off_target: usize,
+    pub sections: Vec<Section>,
+}
+
+impl ProfileReport {
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        let run = self.run_id.as_ref().map(|run_id| format!(" (run {})", run_id)).unwrap_or_default();
+        let _ = writeln!(out, "# Data profile of {}{}\n", self.dataset, run);
+        let _ = writeln!(
+            out,
+            "{} files over {} days. Transaction distributions are read from `{}`. Shares more than {} standard errors from their target are marked ⚠️.\n",
+            self.files, self.dates, self.table, self.tolerance_standard_errors
+        );
+        for section in &self.sections {
+            let _ = writeln!(out, "## {}\n\n{}: {} rows", section.title, section.description, section.population);
+            if let Some(note) = &section.note {
+                let _ = writeln!(out, "\n_{}_", note);
+            }
+            if !section.shares.is_empty() {
+                let _ = writeln!(out, "\n| | Count | Observed | Target | |\n|---|---:|---:|---:|:-:|");
+                for share in &section.shares {
+                    let target = share.target.map(percent).unwrap_or_default();
+                    let icon = match share.on_target {
+                        Some(true) => "✅",
+                        Some(false) => "⚠️",
+                        None => "",
+                    };
+                    let _ = writeln!(out, "| {} | {} | {} | {} | {} |", share.label, share.count, percent(share.observed), target, icon);
+                }
+            }
+            out.push('\n');
+        }
+        let shares: usize = self.sections.iter().map(|section| section.shares.len()).sum();
+        match self.off_target {
+            0 => out.push_str("All shares are on target.\n"),
+            off_target => {
+                let _ = writeln!(out, "⚠️ {} of {} shares are off target.", off_target, shares);
+            }
+        }
+        out
+    }
+}
+
+fn percent(share: f64) -> String {
+    format!("{:.3}%", share * 100.0)
+}
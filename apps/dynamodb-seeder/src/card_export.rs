@@ -0,0 +1,339 @@
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client as S3Client;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use arrow::array::{ArrayRef, Int64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_writer::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use futures::stream::{self, StreamExt};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::table_metadata::METADATA_ITEM_ID;
+
+const CARD_TABLE_NAME: &str = "card";
+const DEFAULT_ROWS_PER_FILE: usize = 1_000_000;
+const DEFAULT_SCAN_SEGMENTS: i32 = 8;
+
+/// How the `pan` attribute appears in the exported table
+pub enum PanExportMode {
+    Omit,
+    /// First 6 and last 4 digits, the rest replaced by `*`
+    Mask,
+    /// AES-256-GCM, written as hex(nonce || ciphertext) next to the key id
+    Encrypt { key_id: String, cipher: Box<Aes256Gcm> },
+}
+
+pub struct CardExportOptions {
+    /// Local directory or s3://bucket/prefix; files land under `<destination>/card/`
+    pub destination: String,
+    pub pan_mode: PanExportMode,
+    pub rows_per_file: usize,
+    pub scan_segments: i32,
+}
+
+impl CardExportOptions {
+    /// Read CARD_EXPORT_PATH (None when unset), CARD_EXPORT_PAN_MODE (omit, mask or encrypt),
+    /// CARD_EXPORT_ENCRYPTION_KEY (64 hex chars) and CARD_EXPORT_ENCRYPTION_KEY_ID
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(destination) = std::env::var("CARD_EXPORT_PATH") else {
+            return Ok(None);
+        };
+
+        let pan_mode = match std::env::var("CARD_EXPORT_PAN_MODE").unwrap_or_else(|_| "omit".to_string()).to_lowercase().as_str() {
+            "omit" => PanExportMode::Omit,
+            "mask" => PanExportMode::Mask,
+            "encrypt" => {
+                let key = std::env::var("CARD_EXPORT_ENCRYPTION_KEY")
+                    .map_err(|_| anyhow!("CARD_EXPORT_ENCRYPTION_KEY env var must be set when CARD_EXPORT_PAN_MODE=encrypt"))?;
+                let key = hex::decode(key.trim()).map_err(|_| anyhow!("CARD_EXPORT_ENCRYPTION_KEY must be hex encoded"))?;
+                let cipher = Aes256Gcm::new_from_slice(&key)
+                    .map_err(|_| anyhow!("CARD_EXPORT_ENCRYPTION_KEY must be 32 bytes (64 hex characters)"))?;
+                let key_id = std::env::var("CARD_EXPORT_ENCRYPTION_KEY_ID").unwrap_or_else(|_| "v1".to_string());
+                PanExportMode::Encrypt { key_id, cipher: Box::new(cipher) }
+            }
+            other => return Err(anyhow!("Unsupported CARD_EXPORT_PAN_MODE: {}. Supported: omit, mask, encrypt", other)),
+        };
+
+        Ok(Some(Self {
+            destination: destination.trim_end_matches('/').to_string(),
+            pan_mode,
+            rows_per_file: std::env::var("CARD_EXPORT_ROWS_PER_FILE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_ROWS_PER_FILE)
+                .max(1),
+            scan_segments: DEFAULT_SCAN_SEGMENTS,
+        }))
+    }
+}
+
+// Files are written here first and only replace the table once the whole export succeeded; the
+// leading underscore keeps Athena and Glue from reading it as data
+const STAGING_NAME: &str = "_card_staging";
+
+enum Destination {
+    Local { dir: std::path::PathBuf, staging: std::path::PathBuf },
+    S3 { client: S3Client, bucket: String, prefix: String, staging_prefix: String },
+}
+
+impl Destination {
+    async fn open(destination: &str) -> Result<Self> {
+        match destination.strip_prefix("s3://") {
+            Some(location) => {
+                let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                let under = |name: &str| if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) };
+                Ok(Destination::S3 { client: S3Client::new(&config), bucket: bucket.to_string(), prefix: under(CARD_TABLE_NAME), staging_prefix: under(STAGING_NAME) })
+            }
+            None => {
+                let root = std::path::Path::new(destination);
+                Ok(Destination::Local { dir: root.join(CARD_TABLE_NAME), staging: root.join(STAGING_NAME) })
+            }
+        }
+    }
+
+    /// Empty the staging area, which an interrupted export may have left files in
+    async fn prepare(&self) -> Result<()> {
+        match self {
+            Destination::Local { staging, .. } => {
+                if staging.exists() {
+                    std::fs::remove_dir_all(staging)?;
+                }
+                std::fs::create_dir_all(staging)?;
+            }
+            Destination::S3 { client, bucket, staging_prefix, .. } => {
+                for key in list_keys(client, bucket, staging_prefix).await? {
+                    client.delete_object().bucket(bucket).key(key).send().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn write(&self, file_name: &str, data: Vec<u8>) -> Result<String> {
+        match self {
+            Destination::Local { staging, .. } => {
+                let path = staging.join(file_name);
+                std::fs::write(&path, data)?;
+                Ok(path.display().to_string())
+            }
+            Destination::S3 { client, bucket, staging_prefix, .. } => {
+                let key = format!("{}/{}", staging_prefix, file_name);
+                client.put_object().bucket(bucket).key(&key).body(ByteStream::from(data)).send().await?;
+                Ok(format!("s3://{}/{}", bucket, key))
+            }
+        }
+    }
+
+    /// Replace the table's files with the staged export. Locally the directories are swapped by
+    /// renames. S3 has no rename: staged files are copied in first (overwriting files of the same
+    /// name), and only then are the previous export's other files and the staging copies deleted,
+    /// so the table is never empty.
+    async fn publish(&self) -> Result<usize> {
+        match self {
+            Destination::Local { dir, staging } => {
+                let previous = dir.with_file_name(format!("{}_previous", STAGING_NAME));
+                if previous.exists() {
+                    std::fs::remove_dir_all(&previous)?;
+                }
+                if dir.exists() {
+                    std::fs::rename(dir, &previous)?;
+                }
+                std::fs::rename(staging, dir)?;
+                if previous.exists() {
+                    std::fs::remove_dir_all(&previous)?;
+                }
+                Ok(std::fs::read_dir(dir)?.count())
+            }
+            Destination::S3 { client, bucket, prefix, staging_prefix } => {
+                let old_keys = list_keys(client, bucket, prefix).await?;
+                let staged_keys = list_keys(client, bucket, staging_prefix).await?;
+                let mut published = std::collections::HashSet::new();
+                for staged_key in &staged_keys {
+                    let key = format!("{}/{}", prefix, &staged_key[staging_prefix.len() + 1..]);
+                    client.copy_object().bucket(bucket).key(&key).copy_source(format!("{}/{}", bucket, staged_key)).send().await?;
+                    published.insert(key);
+                }
+                for key in old_keys.iter().filter(|key| !published.contains(*key)).chain(&staged_keys) {
+                    client.delete_object().bucket(bucket).key(key).send().await?;
+                }
+                Ok(published.len())
+            }
+        }
+    }
+}
+
+async fn list_keys(client: &S3Client, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let page = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(format!("{}/", prefix))
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+        keys.extend(page.contents().iter().filter_map(|object| object.key()).map(str::to_string));
+        continuation_token = page.next_continuation_token().map(|t| t.to_string());
+        if continuation_token.is_none() {
+            return Ok(keys);
+        }
+    }
+}
+
+/// Export every card item to the `card` Parquet table with a parallel scan, one file sequence per
+/// scan segment. The previous export stays in place until every file of the new one is written.
+pub async fn export_card_table(client: &DynamoDbClient, table_name: &str, options: &CardExportOptions) -> Result<()> {
+    let destination = Destination::open(&options.destination).await?;
+    destination.prepare().await?;
+
+    println!("Exporting {} to {}/{} ({} scan segments)...", table_name, options.destination, CARD_TABLE_NAME, options.scan_segments);
+    let start_time = std::time::Instant::now();
+
+    let results: Vec<Result<usize>> = stream::iter(0..options.scan_segments)
+        .map(|segment| export_segment(client, table_name, &destination, options, segment))
+        .buffer_unordered(options.scan_segments as usize)
+        .collect()
+        .await;
+
+    let mut exported_count = 0;
+    for result in results {
+        exported_count += result?;
+    }
+
+    let files = destination.publish().await?;
+    println!("Card export completed: {} rows in {} files in {:.1}s", exported_count, files, start_time.elapsed().as_secs_f64());
+    Ok(())
+}
+
+async fn export_segment(client: &DynamoDbClient, table_name: &str, destination: &Destination, options: &CardExportOptions, segment: i32) -> Result<usize> {
+    let mut rows = Vec::with_capacity(options.rows_per_file.min(DEFAULT_ROWS_PER_FILE));
+    let mut file_index = 0;
+    let mut exported_count = 0;
+    let mut exclusive_start_key = None;
+
+    loop {
+        let page = client
+            .scan()
+            .table_name(table_name)
+            .segment(segment)
+            .total_segments(options.scan_segments)
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
+
+        for item in page.items() {
+            let id = item.get("id").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok());
+            if id.is_some() && id != Some(METADATA_ITEM_ID) {
+                rows.push(item.clone());
+            }
+            if rows.len() >= options.rows_per_file {
+                exported_count += write_card_file(destination, options, segment, file_index, std::mem::take(&mut rows)).await?;
+                file_index += 1;
+            }
+        }
+
+        exclusive_start_key = page.last_evaluated_key().cloned();
+        if exclusive_start_key.is_none() {
+            break;
+        }
+    }
+
+    if !rows.is_empty() {
+        exported_count += write_card_file(destination, options, segment, file_index, rows).await?;
+    }
+    Ok(exported_count)
+}
+
+async fn write_card_file(destination: &Destination, options: &CardExportOptions, segment: i32, file_index: usize, mut rows: Vec<HashMap<String, AttributeValue>>) -> Result<usize> {
+    let row_count = rows.len();
+    rows.sort_by_key(|item| item.get("id").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()).unwrap_or(0));
+
+    let batch = build_card_batch(&rows, &options.pan_mode)?;
+    let data = generate_parquet_data(batch)?;
+    let location = destination.write(&format!("part-{:03}-{:05}.parquet", segment, file_index), data).await?;
+    println!("Wrote {} rows to {}", row_count, location);
+    Ok(row_count)
+}
+
+fn build_card_batch(rows: &[HashMap<String, AttributeValue>], pan_mode: &PanExportMode) -> Result<RecordBatch> {
+    let string_column = |name: &str| -> ArrayRef {
+        Arc::new(StringArray::from(
+            rows.iter()
+                .map(|item| item.get(name).and_then(|v| v.as_s().ok()).cloned())
+                .collect::<Vec<Option<String>>>(),
+        ))
+    };
+
+    let mut fields = vec![Field::new("id", DataType::Int64, false)];
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(Int64Array::from(
+        rows.iter()
+            .map(|item| item.get("id").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<i64>().ok()).unwrap_or(0))
+            .collect::<Vec<i64>>(),
+    ))];
+
+    for name in ["hash_pan", "hash_key_id", "previous_hash_pan", "previous_hash_key_id", "card_type", "bin", "issuer", "issuer_country"] {
+        fields.push(Field::new(name, DataType::Utf8, true));
+        arrays.push(string_column(name));
+    }
+
+    let pans = rows.iter().map(|item| item.get("pan").and_then(|v| v.as_s().ok()));
+    match pan_mode {
+        PanExportMode::Omit => {}
+        PanExportMode::Mask => {
+            fields.push(Field::new("pan_masked", DataType::Utf8, true));
+            arrays.push(Arc::new(StringArray::from(pans.map(|pan| pan.map(|p| mask_pan(p))).collect::<Vec<Option<String>>>())));
+        }
+        PanExportMode::Encrypt { key_id, cipher } => {
+            let encrypted = pans
+                .map(|pan| pan.map(|p| encrypt_pan(cipher, p)).transpose())
+                .collect::<Result<Vec<Option<String>>>>()?;
+            fields.push(Field::new("pan_encrypted", DataType::Utf8, true));
+            arrays.push(Arc::new(StringArray::from(encrypted)));
+            fields.push(Field::new("pan_encryption_key_id", DataType::Utf8, true));
+            arrays.push(Arc::new(StringArray::from(vec![key_id.as_str(); rows.len()])));
+        }
+    }
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+fn mask_pan(pan: &str) -> String {
+    if pan.len() <= 10 {
+        return "*".repeat(pan.len());
+    }
+    format!("{}{}{}", &pan[..6], "*".repeat(pan.len() - 10), &pan[pan.len() - 4..])
+}
+
+fn encrypt_pan(cipher: &Aes256Gcm, pan: &str) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, pan.as_bytes())
+        .map_err(|e| anyhow!("PAN encryption failed: {}", e))?;
+    let mut output = nonce.to_vec();
+    output.extend_from_slice(&ciphertext);
+    Ok(hex::encode(output))
+}
+
+fn generate_parquet_data(batch: RecordBatch) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    {
+        let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(props))?;
+        writer.write(&batch)?;
+        writer.close()?;
+    }
+
+    Ok(buffer)
+}
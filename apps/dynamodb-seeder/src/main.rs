@@ -1,210 +1,396 @@
 use aws_sdk_dynamodb::Client as DynamoDbClient;
-use aws_sdk_dynamodb::types::AttributeValue;
-use fake::Fake;
-use fake::faker::creditcard::en::CreditCardNumber;
-use sha2::{Sha256, Digest};
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::types::{AttributeValue, PutRequest, WriteRequest};
+use futures::stream::{self, StreamExt};
 use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
 
+mod card_export;
+mod pan_generator;
+mod pan_hasher;
+mod table_metadata;
+mod verify;
 
-fn generate_visa_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if clean_pan.starts_with("4") && (clean_pan.len() == 13 || clean_pan.len() == 16 || clean_pan.len() == 19) {
-            return clean_pan;
-        }
-    }
+use card_export::{export_card_table, CardExportOptions};
+use pan_generator::{card_type_name, load_bin_ranges, normalize_brand, PanGenerator};
+use pan_hasher::PanHasher;
+use table_metadata::{get_table_metadata, update_table_metadata, METADATA_ITEM_ID, SEED_STATUS_COMPLETE, SEED_STATUS_IN_PROGRESS};
+
+const DEFAULT_NUM_OF_CARDS: u64 = 100000;
+const DEFAULT_SEED_CONCURRENCY: usize = 64;
+const DEFAULT_CARD_SEED: u64 = 42;
+// DynamoDB BatchWriteItem limit
+const BATCH_WRITE_SIZE: u64 = 25;
+const MAX_BACKOFF_MS: u64 = 5000;
+// Consecutive failed requests a batch write gives up after, even when the errors look transient
+const MAX_WRITE_ERRORS: u32 = 10;
+const CHECKPOINT_INTERVAL_SECS: u64 = 10;
+// Key id reported for items hashed with the original unsalted SHA-256
+const LEGACY_HASH_KEY_ID: &str = "sha256";
+const REHASH_CONCURRENCY: usize = 50;
+
+struct SeedOptions {
+    card_brand: String,
+    bin_table_path: Option<String>,
+    num_cards: u64,
+    concurrency: usize,
+    // CARD_SEED override; otherwise the seed recorded by a previous run (or the default) is used
+    card_seed: Option<u64>,
+    // Ignore any checkpoint and rewrite every item from id 0
+    restart: bool,
 }
 
-fn generate_mastercard_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if clean_pan.starts_with("5") && clean_pan.len() == 16 {
-            return clean_pan;
-        }
+/// Throttling, server-side and transport failures clear up on their own; a missing table, denied
+/// access or invalid item fails every retry the same way
+fn is_retryable<E: ProvideErrorMetadata, R>(error: &SdkError<E, R>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(service) => matches!(
+            service.err().code(),
+            Some("ProvisionedThroughputExceededException" | "ThrottlingException" | "RequestLimitExceeded" | "InternalServerError" | "ServiceUnavailable")
+        ),
+        _ => false,
     }
 }
 
-fn generate_amex_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if (clean_pan.starts_with("34") || clean_pan.starts_with("37")) && clean_pan.len() == 15 {
-            return clean_pan;
+/// Write one BatchWriteItem request, retrying unprocessed items and transient errors with capped backoff
+async fn write_batch(client: &DynamoDbClient, table_name: &str, items: Vec<HashMap<String, AttributeValue>>) -> Result<usize> {
+    let item_count = items.len();
+    let mut requests_to_process = items
+        .into_iter()
+        .map(|item| {
+            Ok(WriteRequest::builder()
+                .put_request(PutRequest::builder().set_item(Some(item)).build()?)
+                .build())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut backoff_ms = 100;
+    let mut errors = 0;
+    while !requests_to_process.is_empty() {
+        match client
+            .batch_write_item()
+            .request_items(table_name, requests_to_process.clone())
+            .send()
+            .await
+        {
+            Ok(response) => {
+                errors = 0;
+                // Check for unprocessed items
+                requests_to_process = response
+                    .unprocessed_items()
+                    .and_then(|unprocessed| unprocessed.get(table_name))
+                    .cloned()
+                    .unwrap_or_default();
+                if requests_to_process.is_empty() {
+                    break;
+                }
+            }
+            Err(e) => {
+                errors += 1;
+                if !is_retryable(&e) || errors >= MAX_WRITE_ERRORS {
+                    return Err(anyhow::anyhow!("Batch write to {} failed after {} attempts: {}", table_name, errors, DisplayErrorContext(&e)));
+                }
+                eprintln!("Batch write error: {}, retrying in {}ms...", e, backoff_ms);
+            }
         }
+        tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
+        backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
     }
+
+    Ok(item_count)
 }
 
-fn generate_discover_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if clean_pan.starts_with("6") && clean_pan.len() == 16 {
-            return clean_pan;
-        }
-    }
+fn build_card_item(id: u64, generator: &PanGenerator, hasher: &PanHasher) -> Result<HashMap<String, AttributeValue>> {
+    let card = generator.card_for_id(id)?;
+    let hash_pan = hasher.hash(&card.pan);
+
+    let mut item = HashMap::new();
+    item.insert("id".to_string(), AttributeValue::N(id.to_string()));
+    item.insert("pan".to_string(), AttributeValue::S(card.pan));
+    item.insert("hash_pan".to_string(), AttributeValue::S(hash_pan));
+    item.insert("hash_key_id".to_string(), AttributeValue::S(hasher.key_id().to_string()));
+    item.insert("card_type".to_string(), AttributeValue::S(card.card_type));
+    item.insert("bin".to_string(), AttributeValue::S(card.bin));
+    item.insert("issuer".to_string(), AttributeValue::S(card.issuer));
+    item.insert("issuer_country".to_string(), AttributeValue::S(card.country));
+    Ok(item)
 }
 
-fn generate_jcb_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if clean_pan.starts_with("35") && clean_pan.len() == 16 {
-            return clean_pan;
-        }
-    }
+/// Build the card generator for seeding or verifying, using the CARD_SEED override, the seed
+/// recorded by a previous run, or the default seed, in that order
+fn build_generator(card_brand: &str, bin_table_path: Option<&str>, card_seed: Option<u64>, recorded_seed: Option<u64>) -> Result<(PanGenerator, u64)> {
+    let brand = normalize_brand(card_brand)?;
+    let seed = card_seed.or(recorded_seed).unwrap_or(DEFAULT_CARD_SEED);
+    let generator = PanGenerator::new(load_bin_ranges(bin_table_path)?, &brand, seed)?;
+    Ok((generator, seed))
 }
 
-fn generate_diners_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if (clean_pan.starts_with("30") || clean_pan.starts_with("36") || clean_pan.starts_with("38")) && clean_pan.len() == 14 {
-            return clean_pan;
+/// Seed ids [0, num_cards) with at most `concurrency` batch writes in flight.
+///
+/// Cards are a pure function of (seed, BIN table, id), so rewriting an id is idempotent. The
+/// highest id below which every batch is confirmed is checkpointed in the metadata item, and a
+/// rerun with the same seed, BIN table and hash key resumes from there instead of starting over.
+async fn seed_dynamodb(client: &DynamoDbClient, table_name: &str, hasher: &PanHasher, options: &SeedOptions) -> Result<()> {
+    let metadata = get_table_metadata(client, table_name).await?;
+    let (generator, seed) = build_generator(&options.card_brand, options.bin_table_path.as_deref(), options.card_seed, metadata.card_seed)?;
+    generator.check_capacity(options.num_cards)?;
+    let num_cards = options.num_cards;
+
+    let same_universe = metadata.card_seed == Some(seed)
+        && metadata.bin_fingerprint.as_deref() == Some(generator.fingerprint())
+        && metadata.hash_key_id.as_deref() == Some(hasher.key_id());
+    if metadata.seed_status.is_some() && !same_universe && !options.restart {
+        return Err(anyhow::anyhow!(
+            "{} was seeded with a different seed, BIN table or hash key; rerunning would change PANs behind existing hash_pans. Set SEED_RESTART=true to reseed anyway",
+            table_name
+        ));
+    }
+
+    let start_id = if options.restart || !same_universe {
+        0
+    } else if metadata.seed_status.as_deref() == Some(SEED_STATUS_COMPLETE) {
+        let seeded_count = metadata.card_count.unwrap_or(0);
+        if seeded_count >= num_cards {
+            println!("{} already holds {} cards for seed {}, nothing to do", table_name, seeded_count, seed);
+            return Ok(());
         }
+        seeded_count
+    } else if metadata.target_card_count == Some(num_cards) {
+        metadata.seeded_through.unwrap_or(0)
+    } else {
+        0
+    };
+
+    update_table_metadata(
+        client,
+        table_name,
+        vec![
+            ("card_seed", AttributeValue::N(seed.to_string())),
+            ("bin_fingerprint", AttributeValue::S(generator.fingerprint().to_string())),
+            // Recorded up front too, so a run that crashes before completing can be resumed
+            ("hash_key_id", AttributeValue::S(hasher.key_id().to_string())),
+            ("seed_status", AttributeValue::S(SEED_STATUS_IN_PROGRESS.to_string())),
+            ("target_card_count", AttributeValue::N(num_cards.to_string())),
+            ("seeded_through", AttributeValue::N(start_id.to_string())),
+        ],
+        &[],
+    )
+    .await?;
+
+    if start_id > 0 {
+        println!("Resuming from id {} (checkpoint of a previous run)", start_id);
     }
-}
+    println!("Seeding DynamoDB with {} {} PANs (seed {}, {} concurrent batch writes)...", num_cards - start_id, card_type_name(&normalize_brand(&options.card_brand)?), seed, options.concurrency);
+    let start_time = std::time::Instant::now();
+    
+    let progress_interval = ((num_cards - start_id) / 20).max(BATCH_WRITE_SIZE);
+    let mut written_count = 0;
+    let mut next_progress = progress_interval;
+    // Batches finish out of order; the checkpoint only advances over a contiguous prefix
+    let mut confirmed_through = start_id;
+    let mut completed_chunks = BTreeSet::new();
+    let mut last_checkpoint = std::time::Instant::now();
 
-fn generate_unionpay_pan() -> String {
-    loop {
-        let pan: String = CreditCardNumber().fake();
-        let clean_pan = pan.replace(" ", "").replace("-", "");
-        if clean_pan.starts_with("62") && (clean_pan.len() >= 16 && clean_pan.len() <= 19) {
-            return clean_pan;
+    let mut write_stream = stream::iter((start_id..num_cards).step_by(BATCH_WRITE_SIZE as usize))
+        .map(|chunk_start| {
+            let chunk_end = (chunk_start + BATCH_WRITE_SIZE).min(num_cards);
+            let items = (chunk_start..chunk_end)
+                .map(|id| build_card_item(id, &generator, hasher))
+                .collect::<Result<Vec<_>>>();
+            async move { write_batch(client, table_name, items?).await.map(|count| (chunk_start, count)) }
+        })
+        .buffer_unordered(options.concurrency);
+
+    while let Some(result) = write_stream.next().await {
+        let (chunk_start, count) = result?;
+        written_count += count;
+        completed_chunks.insert(chunk_start);
+        while completed_chunks.remove(&confirmed_through) {
+            confirmed_through = (confirmed_through + BATCH_WRITE_SIZE).min(num_cards);
+        }
+
+        if last_checkpoint.elapsed().as_secs() >= CHECKPOINT_INTERVAL_SECS {
+            update_table_metadata(client, table_name, vec![("seeded_through", AttributeValue::N(confirmed_through.to_string()))], &[]).await?;
+            last_checkpoint = std::time::Instant::now();
+        }
+        if written_count >= next_progress as usize {
+            println!("Written {}/{} items, confirmed through id {} ({:.0} items/s)...", written_count, num_cards - start_id, confirmed_through, written_count as f64 / start_time.elapsed().as_secs_f64());
+            next_progress += progress_interval;
         }
     }
-}
 
-fn generate_random_card_pan() -> (String, String) {
-    let card_types = [
-        ("Visa", generate_visa_pan as fn() -> String),
-        ("Mastercard", generate_mastercard_pan as fn() -> String),
-        ("American Express", generate_amex_pan as fn() -> String),
-        ("Discover", generate_discover_pan as fn() -> String),
-        ("JCB", generate_jcb_pan as fn() -> String),
-        ("Diners Club", generate_diners_pan as fn() -> String),
-        ("UnionPay", generate_unionpay_pan as fn() -> String),
-    ];
-    
-    let random_index = (0..card_types.len()).fake::<usize>();
-    let (card_type, generator) = &card_types[random_index];
-    (card_type.to_string(), generator())
+    update_table_metadata(
+        client,
+        table_name,
+        vec![
+            ("hash_key_id", AttributeValue::S(hasher.key_id().to_string())),
+            ("card_count", AttributeValue::N(num_cards.to_string())),
+            ("seed_status", AttributeValue::S(SEED_STATUS_COMPLETE.to_string())),
+            ("seeded_through", AttributeValue::N(num_cards.to_string())),
+        ],
+        &["previous_hash_key_id", "target_card_count"],
+    )
+    .await?;
+
+    println!("DynamoDB seeding completed: {} items with hash key {} in {:.1}s", written_count, hasher.key_id(), start_time.elapsed().as_secs_f64());
+    Ok(())
 }
 
-fn get_card_generator(card_brand: &str) -> Result<(String, fn() -> String)> {
-    match card_brand.to_uppercase().as_str() {
-        "VISA" => Ok(("Visa".to_string(), generate_visa_pan as fn() -> String)),
-        "MASTERCARD" => Ok(("Mastercard".to_string(), generate_mastercard_pan as fn() -> String)),
-        "AMEX" | "AMERICAN_EXPRESS" => Ok(("American Express".to_string(), generate_amex_pan as fn() -> String)),
-        "DISCOVER" => Ok(("Discover".to_string(), generate_discover_pan as fn() -> String)),
-        "JCB" => Ok(("JCB".to_string(), generate_jcb_pan as fn() -> String)),
-        "DINERS" | "DINERS_CLUB" => Ok(("Diners Club".to_string(), generate_diners_pan as fn() -> String)),
-        "UNIONPAY" => Ok(("UnionPay".to_string(), generate_unionpay_pan as fn() -> String)),
-        "MIXED" | "ALL" => Ok(("Mixed".to_string(), || generate_random_card_pan().1)),
-        _ => Err(anyhow::anyhow!("Unsupported card brand: {}. Supported: VISA, MASTERCARD, AMEX, DISCOVER, JCB, DINERS, UNIONPAY, MIXED", card_brand))
+/// Re-hash every item under `hasher`, keeping the old hash in previous_hash_pan so data generated
+/// before the rotation can still be joined. The metadata item only switches to the new key once all
+/// items carry it, so generator jobs running mid-rotation keep using the old key consistently.
+async fn rehash_dynamodb(client: &DynamoDbClient, table_name: &str, hasher: &PanHasher) -> Result<()> {
+    let previous_key_id = get_table_metadata(client, table_name)
+        .await?
+        .hash_key_id
+        .unwrap_or_else(|| LEGACY_HASH_KEY_ID.to_string());
+    if previous_key_id == hasher.key_id() {
+        return Err(anyhow::anyhow!("Hash key {} is already active; choose a new HASH_PAN_KEY_ID to rotate", previous_key_id));
     }
-}
 
-fn hash_pan(pan: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(pan.as_bytes());
-    hex::encode(hasher.finalize())
-}
+    println!("Re-hashing {} from key {} to key {}...", table_name, previous_key_id, hasher.key_id());
 
-async fn seed_dynamodb(client: &DynamoDbClient, table_name: &str, card_brand: &str) -> Result<()> {
-    let (card_type, generator) = get_card_generator(card_brand)?;
-    
-    println!("Seeding DynamoDB with 100k {} PANs...", card_type);
-    
-    let items: Vec<_> = (0..100000)
-        .map(|i| {
-            let (final_card_type, pan) = if card_type == "Mixed" {
-                generate_random_card_pan()
-            } else {
-                (card_type.clone(), generator())
-            };
-            let hash_pan = hash_pan(&pan);
-            
-            let mut item = std::collections::HashMap::new();
-            item.insert("id".to_string(), AttributeValue::N(i.to_string()));
-            item.insert("pan".to_string(), AttributeValue::S(pan));
-            item.insert("hash_pan".to_string(), AttributeValue::S(hash_pan));
-            item.insert("card_type".to_string(), AttributeValue::S(final_card_type));
-            item
-        })
-        .collect();
+    let mut rehashed_count = 0;
+    let mut skipped_count = 0;
+    let mut exclusive_start_key = None;
+    loop {
+        let page = client
+            .scan()
+            .table_name(table_name)
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
 
-    // Batch write in chunks of 25
-    let mut written_count = 0;
-    for chunk in items.chunks(25) {
-        let write_requests: Vec<_> = chunk
+        let items: Vec<_> = page
+            .items()
             .iter()
-            .map(|item| {
-                aws_sdk_dynamodb::types::WriteRequest::builder()
-                    .put_request(
-                        aws_sdk_dynamodb::types::PutRequest::builder()
-                            .set_item(Some(item.clone()))
-                            .build()
-                            .unwrap()
-                    )
-                    .build()
-            })
+            .filter(|item| item.get("id").and_then(|v| v.as_n().ok()).map(|id| id != &METADATA_ITEM_ID.to_string()).unwrap_or(false))
+            .cloned()
             .collect();
 
-        let mut requests_to_process = write_requests;
-        
-        // Retry unprocessed items
-        while !requests_to_process.is_empty() {
-            match client
-                .batch_write_item()
-                .request_items(table_name, requests_to_process.clone())
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    written_count += requests_to_process.len();
-                    
-                    // Check for unprocessed items
-                    if let Some(unprocessed) = response.unprocessed_items() {
-                        if let Some(table_requests) = unprocessed.get(table_name) {
-                            requests_to_process = table_requests.clone();
-                            println!("Retrying {} unprocessed items...", requests_to_process.len());
-                            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        } else {
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Batch write error: {}, retrying...", e);
-                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                }
+        let results: Vec<Result<bool>> = stream::iter(items)
+            .map(|item| rehash_item(client, table_name, hasher, item))
+            .buffer_unordered(REHASH_CONCURRENCY)
+            .collect()
+            .await;
+
+        for result in results {
+            if result? {
+                rehashed_count += 1;
+            } else {
+                skipped_count += 1;
             }
         }
-        
-        if written_count % 1000 == 0 {
-            println!("Written {} items...", written_count);
+        println!("Re-hashed {} items ({} already on key {})...", rehashed_count, skipped_count, hasher.key_id());
+
+        exclusive_start_key = page.last_evaluated_key().cloned();
+        if exclusive_start_key.is_none() {
+            break;
         }
     }
 
-    println!("DynamoDB seeding completed!");
+    update_table_metadata(
+        client,
+        table_name,
+        vec![
+            ("hash_key_id", AttributeValue::S(hasher.key_id().to_string())),
+            ("previous_hash_key_id", AttributeValue::S(previous_key_id.clone())),
+        ],
+        &[],
+    )
+    .await?;
+
+    println!("Re-hash completed: {} items moved to key {}, previous key {} kept in previous_hash_pan", rehashed_count, hasher.key_id(), previous_key_id);
     Ok(())
 }
 
+// Returns false when the item already carries the new key (e.g. a rerun after a partial rotation)
+async fn rehash_item(client: &DynamoDbClient, table_name: &str, hasher: &PanHasher, item: HashMap<String, AttributeValue>) -> Result<bool> {
+    let item_key_id = item
+        .get("hash_key_id")
+        .and_then(|v| v.as_s().ok())
+        .map(|s| s.as_str())
+        .unwrap_or(LEGACY_HASH_KEY_ID);
+    if item_key_id == hasher.key_id() {
+        return Ok(false);
+    }
+
+    let (Some(id), Some(pan), Some(old_hash_pan)) = (item.get("id"), item.get("pan"), item.get("hash_pan")) else {
+        return Err(anyhow::anyhow!("Item {:?} is missing id, pan or hash_pan", item.get("id")));
+    };
+    let pan = pan.as_s().map_err(|_| anyhow::anyhow!("Item {:?} has a non-string pan", id))?;
+
+    client
+        .update_item()
+        .table_name(table_name)
+        .key("id", id.clone())
+        .update_expression("SET hash_pan = :hash_pan, hash_key_id = :key_id, previous_hash_pan = :previous_hash_pan, previous_hash_key_id = :previous_key_id")
+        .condition_expression("attribute_not_exists(hash_key_id) OR hash_key_id <> :key_id")
+        .expression_attribute_values(":hash_pan", AttributeValue::S(hasher.hash(pan)))
+        .expression_attribute_values(":key_id", AttributeValue::S(hasher.key_id().to_string()))
+        .expression_attribute_values(":previous_hash_pan", old_hash_pan.clone())
+        .expression_attribute_values(":previous_key_id", AttributeValue::S(item_key_id.to_string()))
+        .send()
+        .await?;
+
+    Ok(true)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let table_name = env::var("HASH_PAN_TABLE_NAME")
         .map_err(|_| anyhow::anyhow!("HASH_PAN_TABLE_NAME env var must be set"))?;
     
-    let card_brand = env::var("CARD_BRAND").unwrap_or_else(|_| "MIXED".to_string());
+    let num_cards = env::var("NUM_OF_CARDS")
+        .ok()
+        .map(|v| match v.parse::<u64>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(anyhow::anyhow!("NUM_OF_CARDS must be a positive integer, got {}", v)),
+        })
+        .transpose()?
+        .unwrap_or(DEFAULT_NUM_OF_CARDS);
+    let card_seed = env::var("CARD_SEED")
+        .ok()
+        .map(|v| v.parse::<u64>().map_err(|_| anyhow::anyhow!("CARD_SEED must be an unsigned integer, got {}", v)))
+        .transpose()?;
+    let options = SeedOptions {
+        card_brand: env::var("CARD_BRAND").unwrap_or_else(|_| "MIXED".to_string()),
+        bin_table_path: env::var("BIN_TABLE_PATH").ok(),
+        num_cards,
+        concurrency: env::var("SEED_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_SEED_CONCURRENCY)
+            .max(1),
+        card_seed,
+        restart: env::var("SEED_RESTART").map(|v| v.to_lowercase() == "true").unwrap_or(false),
+    };
+
+    let seeder_mode = env::var("SEEDER_MODE").unwrap_or_else(|_| "seed".to_string());
+    let hasher = PanHasher::from_env()?;
+    let export_options = CardExportOptions::from_env()?;
 
     let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
     let client = DynamoDbClient::new(&config);
 
-    seed_dynamodb(&client, &table_name, &card_brand).await?;
+    match seeder_mode.to_lowercase().as_str() {
+        "seed" => seed_dynamodb(&client, &table_name, &hasher, &options).await?,
+        "rehash" => rehash_dynamodb(&client, &table_name, &hasher).await?,
+        "verify" => verify::verify_dynamodb(&client, &table_name, &hasher, &options).await?,
+        "export" => {
+            let export_options = export_options
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("CARD_EXPORT_PATH env var must be set for SEEDER_MODE=export"))?;
+            return export_card_table(&client, &table_name, export_options).await;
+        }
+        _ => return Err(anyhow::anyhow!("Unsupported SEEDER_MODE: {}. Supported: seed, rehash, verify, export", seeder_mode)),
+    }
+
+    // Refresh the card table after the universe or its hashes changed
+    if let (Some(export_options), "seed" | "rehash") = (&export_options, seeder_mode.to_lowercase().as_str()) {
+        export_card_table(&client, &table_name, export_options).await?;
+    }
     Ok(())
 }
@@ -0,0 +1,396 @@
+use anyhow::{anyhow, bail, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_BIN_TABLE: &str = include_str!("../bin_table.json");
+const SUPPORTED_BRANDS: &str = "VISA, MASTERCARD, AMEX, DISCOVER, JCB, DINERS, UNIONPAY, MIXED";
+const FEISTEL_ROUNDS: usize = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BinRange {
+    pub brand: String,
+    pub issuer: String,
+    pub country: String,
+    pub bin_start: String,
+    pub bin_end: String,
+    pub lengths: Vec<usize>,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BinTable {
+    ranges: Vec<BinRange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedCard {
+    pub pan: String,
+    pub card_type: String,
+    pub bin: String,
+    pub issuer: String,
+    pub country: String,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Normalize brand aliases (AMERICAN_EXPRESS, DINERS_CLUB, ALL) to the codes used in the BIN table
+pub fn normalize_brand(card_brand: &str) -> Result<String> {
+    match card_brand.to_uppercase().as_str() {
+        "VISA" => Ok("VISA".to_string()),
+        "MASTERCARD" => Ok("MASTERCARD".to_string()),
+        "AMEX" | "AMERICAN_EXPRESS" => Ok("AMEX".to_string()),
+        "DISCOVER" => Ok("DISCOVER".to_string()),
+        "JCB" => Ok("JCB".to_string()),
+        "DINERS" | "DINERS_CLUB" => Ok("DINERS".to_string()),
+        "UNIONPAY" => Ok("UNIONPAY".to_string()),
+        "MIXED" | "ALL" => Ok("MIXED".to_string()),
+        _ => Err(anyhow!("Unsupported card brand: {}. Supported: {}", card_brand, SUPPORTED_BRANDS)),
+    }
+}
+
+/// Display name stored in the `card_type` attribute
+pub fn card_type_name(brand: &str) -> &'static str {
+    match brand {
+        "VISA" => "Visa",
+        "MASTERCARD" => "Mastercard",
+        "AMEX" => "American Express",
+        "DISCOVER" => "Discover",
+        "JCB" => "JCB",
+        "DINERS" => "Diners Club",
+        "UNIONPAY" => "UnionPay",
+        _ => "Mixed",
+    }
+}
+
+/// Load BIN ranges from `path`, or from the bundled bin_table.json when no path is given
+pub fn load_bin_ranges(path: Option<&str>) -> Result<Vec<BinRange>> {
+    let content = match path {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read BIN table {}: {}", path, e))?,
+        None => DEFAULT_BIN_TABLE.to_string(),
+    };
+    let table: BinTable = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Invalid BIN table {}: {}", path.unwrap_or("bin_table.json"), e))?;
+
+    let mut ranges = Vec::with_capacity(table.ranges.len());
+    for mut range in table.ranges {
+        range.brand = normalize_brand(&range.brand)?;
+        validate_range(&range)?;
+        if let Some(other) = ranges.iter().find(|other| ranges_overlap(other, &range)) {
+            bail!("BIN ranges {} and {} cover the same PANs; split one around the other", range_label(other), range_label(&range));
+        }
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+fn range_label(range: &BinRange) -> String {
+    format!("{} {} {}-{}", range.brand, range.issuer, range.bin_start, range.bin_end)
+}
+
+/// Whether two ranges can produce the same PAN: they share a PAN length and their BIN intervals
+/// intersect once the shorter BINs are widened to the longer one's digits (3742 spans 374200-374299)
+fn ranges_overlap(a: &BinRange, b: &BinRange) -> bool {
+    if !a.lengths.iter().any(|length| b.lengths.contains(length)) {
+        return false;
+    }
+    let digits = a.bin_start.len().max(b.bin_start.len());
+    let widen = |bin: &str, fill: char| format!("{}{}", bin, fill.to_string().repeat(digits - bin.len()));
+    widen(&a.bin_start, '0') <= widen(&b.bin_end, '9') && widen(&b.bin_start, '0') <= widen(&a.bin_end, '9')
+}
+
+fn validate_range(range: &BinRange) -> Result<()> {
+    let label = range_label(range);
+    if range.brand == "MIXED" {
+        bail!("BIN range {}: brand must be a concrete card brand", label);
+    }
+    if range.bin_start.is_empty() || !range.bin_start.chars().all(|c| c.is_ascii_digit()) {
+        bail!("BIN range {}: bin_start must be numeric", label);
+    }
+    if range.bin_end.len() != range.bin_start.len() || !range.bin_end.chars().all(|c| c.is_ascii_digit()) {
+        bail!("BIN range {}: bin_end must be numeric and the same length as bin_start", label);
+    }
+    if range.bin_end < range.bin_start {
+        bail!("BIN range {}: bin_end is lower than bin_start", label);
+    }
+    if range.lengths.is_empty() {
+        bail!("BIN range {}: at least one PAN length is required", label);
+    }
+    for length in &range.lengths {
+        if *length < 12 || *length > 19 || *length < range.bin_start.len() + 2 {
+            bail!("BIN range {}: PAN length {} must be 12-19 and leave room for an account number and check digit", label, length);
+        }
+    }
+    if range.weight == 0 {
+        bail!("BIN range {}: weight must be greater than 0", label);
+    }
+    Ok(())
+}
+
+/// Luhn check digit for a PAN without its final digit
+pub fn luhn_check_digit(partial_pan: &str) -> u32 {
+    let sum: u32 = partial_pan
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| {
+            if i % 2 == 0 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    (10 - (sum % 10)) % 10
+}
+
+/// Keyed bijection on [0, domain): a balanced Feistel network over the next even power of two,
+/// cycle-walked back into the domain. Used to scatter sequential account slots across a BIN range.
+struct Permutation {
+    domain: u128,
+    half_bits: u32,
+    keys: [u64; FEISTEL_ROUNDS],
+}
+
+impl Permutation {
+    fn new<R: Rng>(domain: u128, rng: &mut R) -> Self {
+        let bits = 128 - (domain.max(2) - 1).leading_zeros();
+        let half_bits = bits.div_ceil(2);
+        let mut keys = [0u64; FEISTEL_ROUNDS];
+        for key in keys.iter_mut() {
+            *key = rng.gen();
+        }
+        Self { domain, half_bits, keys }
+    }
+
+    fn apply(&self, value: u128) -> u128 {
+        let mut value = self.feistel(value);
+        while value >= self.domain {
+            value = self.feistel(value);
+        }
+        value
+    }
+
+    fn feistel(&self, value: u128) -> u128 {
+        let mask = (1u128 << self.half_bits) - 1;
+        let (mut left, mut right) = (value >> self.half_bits, value & mask);
+        for key in &self.keys {
+            let round = splitmix64(right as u64 ^ key) as u128 & mask;
+            (left, right) = (right, left ^ round);
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+struct RangeSlots {
+    range: BinRange,
+    // Position of the range within the weighted round-robin cycle
+    cycle_start: u64,
+    capacity: u128,
+    permutation: Permutation,
+}
+
+/// Deterministic card generator: the card for an id depends only on the seed and the BIN ranges,
+/// and distinct ids always map to distinct PANs. Each range permutes its own PAN space, so this
+/// relies on load_bin_ranges rejecting ranges that overlap.
+///
+/// Ids are dealt to ranges by weighted round-robin, giving each range a dense sequence of slot
+/// numbers. A keyed permutation scatters each slot over the range's (length, BIN, account) space.
+pub struct PanGenerator {
+    ranges: Vec<RangeSlots>,
+    total_weight: u64,
+    fingerprint: String,
+}
+
+impl PanGenerator {
+    /// Build a generator over the ranges matching `card_brand` (all ranges for MIXED)
+    pub fn new(ranges: Vec<BinRange>, card_brand: &str, seed: u64) -> Result<Self> {
+        let brand = normalize_brand(card_brand)?;
+        let ranges: Vec<BinRange> = ranges
+            .into_iter()
+            .filter(|range| brand == "MIXED" || range.brand == brand)
+            .collect();
+        if ranges.is_empty() {
+            bail!("No BIN ranges configured for card brand {}", brand);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(serde_json::to_vec(&ranges)?);
+        let fingerprint = hex::encode(hasher.finalize());
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut cycle_start = 0;
+        let mut slots = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let capacity = range_capacity(&range);
+            let permutation = Permutation::new(capacity, &mut rng);
+            let weight = range.weight as u64;
+            slots.push(RangeSlots { range, cycle_start, capacity, permutation });
+            cycle_start += weight;
+        }
+
+        Ok(Self { ranges: slots, total_weight: cycle_start, fingerprint })
+    }
+
+    /// SHA-256 of the selected BIN ranges; a different fingerprint means ids map to different cards
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Fail if any range would run out of slots before `num_cards` ids are dealt
+    pub fn check_capacity(&self, num_cards: u64) -> Result<()> {
+        let full_cycles = num_cards / self.total_weight;
+        let remainder = num_cards % self.total_weight;
+        for slots in &self.ranges {
+            let weight = slots.range.weight as u64;
+            let partial = remainder.saturating_sub(slots.cycle_start).min(weight);
+            let needed = full_cycles as u128 * weight as u128 + partial as u128;
+            if needed > slots.capacity {
+                bail!(
+                    "BIN range {} {} {}-{} can only produce {} unique PANs, {} required for {} cards",
+                    slots.range.brand, slots.range.issuer, slots.range.bin_start, slots.range.bin_end,
+                    slots.capacity, needed, num_cards
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The Luhn-valid card for `id`; the same id always yields the same card
+    pub fn card_for_id(&self, id: u64) -> Result<GeneratedCard> {
+        let position = id % self.total_weight;
+        let slots = self
+            .ranges
+            .iter()
+            .rev()
+            .find(|slots| slots.cycle_start <= position)
+            .ok_or_else(|| anyhow!("No BIN range for id {}", id))?;
+        let slot = (id / self.total_weight) as u128 * slots.range.weight as u128 + (position - slots.cycle_start) as u128;
+        if slot >= slots.capacity {
+            bail!("BIN range {} {} exhausted at id {}", slots.range.brand, slots.range.issuer, id);
+        }
+        Ok(card_for_slot(&slots.range, slots.permutation.apply(slot)))
+    }
+}
+
+fn range_capacity(range: &BinRange) -> u128 {
+    let bins = range.bin_end.parse::<u128>().unwrap_or(0) - range.bin_start.parse::<u128>().unwrap_or(0) + 1;
+    range
+        .lengths
+        .iter()
+        .map(|length| bins * accounts_per_bin(range, *length))
+        .sum()
+}
+
+fn accounts_per_bin(range: &BinRange, length: usize) -> u128 {
+    10_u128.pow((length - range.bin_start.len() - 1) as u32)
+}
+
+// Decode a slot into (length, BIN, account number) and append the Luhn check digit
+fn card_for_slot(range: &BinRange, mut slot: u128) -> GeneratedCard {
+    let bin_len = range.bin_start.len();
+    let bin_start: u128 = range.bin_start.parse().unwrap_or(0);
+    let bins = range.bin_end.parse::<u128>().unwrap_or(bin_start) - bin_start + 1;
+
+    let mut length = range.lengths[range.lengths.len() - 1];
+    for candidate in &range.lengths {
+        let slots_for_length = bins * accounts_per_bin(range, *candidate);
+        if slot < slots_for_length {
+            length = *candidate;
+            break;
+        }
+        slot -= slots_for_length;
+    }
+
+    let accounts = accounts_per_bin(range, length);
+    let bin = format!("{:0width$}", bin_start + slot / accounts, width = bin_len);
+    let mut pan = format!("{}{:0width$}", bin, slot % accounts, width = length - bin_len - 1);
+    let check_digit = luhn_check_digit(&pan);
+    pan.push(char::from_digit(check_digit, 10).unwrap_or('0'));
+
+    GeneratedCard {
+        pan,
+        card_type: card_type_name(&range.brand).to_string(),
+        bin,
+        issuer: range.issuer.clone(),
+        country: range.country.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(bin_start: &str, bin_end: &str, lengths: &[usize]) -> BinRange {
+        BinRange {
+            brand: "AMEX".to_string(),
+            issuer: "Test".to_string(),
+            country: "USA".to_string(),
+            bin_start: bin_start.to_string(),
+            bin_end: bin_end.to_string(),
+            lengths: lengths.to_vec(),
+            weight: 1,
+        }
+    }
+
+    #[test]
+    fn luhn_check_digit_matches_known_pans() {
+        for pan in ["79927398713", "4111111111111111", "378282246310005", "6011111111111117", "5555555555554444", "3530111333300000"] {
+            let (partial, check) = pan.split_at(pan.len() - 1);
+            assert_eq!(luhn_check_digit(partial), check.parse::<u32>().unwrap(), "{}", pan);
+        }
+    }
+
+    #[test]
+    fn permutation_is_a_bijection_on_small_domains() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for domain in [1u128, 2, 3, 10, 97, 256, 1000, 4099] {
+            let permutation = Permutation::new(domain, &mut rng);
+            let mut values: Vec<u128> = (0..domain).map(|value| permutation.apply(value)).collect();
+            values.sort_unstable();
+            assert_eq!(values, (0..domain).collect::<Vec<_>>(), "domain {}", domain);
+        }
+    }
+
+    #[test]
+    fn ranges_overlap_compares_bins_widened_to_the_same_digits() {
+        assert!(ranges_overlap(&range("340000", "379999", &[15]), &range("3742", "3742", &[15])));
+        assert!(ranges_overlap(&range("3742", "3742", &[15]), &range("374200", "374200", &[15])));
+        // Adjacent ranges, and ranges with no PAN length in common, never produce the same PAN
+        assert!(!ranges_overlap(&range("340000", "374199", &[15]), &range("3742", "3742", &[15])));
+        assert!(!ranges_overlap(&range("3742", "3742", &[15]), &range("374300", "379999", &[15])));
+        assert!(!ranges_overlap(&range("340000", "379999", &[16]), &range("3742", "3742", &[15])));
+    }
+
+    #[test]
+    fn load_bin_ranges_rejects_overlapping_ranges() {
+        assert!(load_bin_ranges(None).is_ok());
+
+        let path = std::env::temp_dir().join(format!("bin_table_overlap_{}.json", std::process::id()));
+        let table = |ranges: &[BinRange]| serde_json::json!({ "ranges": ranges }).to_string();
+        std::fs::write(&path, table(&[range("340000", "379999", &[15]), range("3742", "3742", &[15])])).unwrap();
+        let overlapping = load_bin_ranges(path.to_str());
+        std::fs::write(&path, table(&[range("340000", "374199", &[15]), range("3742", "3742", &[15]), range("374300", "379999", &[15])])).unwrap();
+        let adjacent = load_bin_ranges(path.to_str());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(overlapping.unwrap_err().to_string().contains("cover the same PANs"));
+        assert_eq!(adjacent.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Minimum secret length so the key cannot be brute-forced alongside the BIN space
+const MIN_SECRET_LEN: usize = 32;
+
+/// Keyed HMAC-SHA256 hasher for PANs; `key_id` is stored next to every hash it produces
+#[derive(Clone)]
+pub struct PanHasher {
+    key_id: String,
+    mac: HmacSha256,
+}
+
+impl PanHasher {
+    pub fn new(key_id: &str, secret: &str) -> Result<Self> {
+        if key_id.is_empty() {
+            return Err(anyhow!("HASH_PAN_KEY_ID must not be empty"));
+        }
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(anyhow!("HASH_PAN_SECRET must be at least {} characters", MIN_SECRET_LEN));
+        }
+        let mac = HmacSha256::new_from_slice(secret.as_bytes())
+            .map_err(|e| anyhow!("Invalid HASH_PAN_SECRET: {}", e))?;
+        Ok(Self { key_id: key_id.to_string(), mac })
+    }
+
+    /// Build a hasher from HASH_PAN_KEY_ID (default "v1") and HASH_PAN_SECRET
+    pub fn from_env() -> Result<Self> {
+        let key_id = std::env::var("HASH_PAN_KEY_ID").unwrap_or_else(|_| "v1".to_string());
+        let secret = std::env::var("HASH_PAN_SECRET")
+            .map_err(|_| anyhow!("HASH_PAN_SECRET env var must be set"))?;
+        Self::new(&key_id, &secret)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn hash(&self, pan: &str) -> String {
+        let mut mac = self.mac.clone();
+        mac.update(pan.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
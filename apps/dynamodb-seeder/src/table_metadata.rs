@@ -0,0 +1,83 @@
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_dynamodb::types::AttributeValue;
+use anyhow::Result;
+use std::collections::HashMap;
+
+// Reserved id of the item that records table-wide settings such as the active hash key
+pub const METADATA_ITEM_ID: i64 = -1;
+
+pub const SEED_STATUS_IN_PROGRESS: &str = "in_progress";
+pub const SEED_STATUS_COMPLETE: &str = "complete";
+
+/// Table-wide settings kept in the metadata item. `hash_key_id` and `card_count` are read by
+/// generator jobs; the rest lets the seeder resume or verify a previous run.
+#[derive(Debug, Clone, Default)]
+pub struct TableMetadata {
+    pub hash_key_id: Option<String>,
+    pub card_count: Option<u64>,
+    pub card_seed: Option<u64>,
+    pub bin_fingerprint: Option<String>,
+    pub seed_status: Option<String>,
+    pub target_card_count: Option<u64>,
+    pub seeded_through: Option<u64>,
+}
+
+pub async fn get_table_metadata(client: &DynamoDbClient, table_name: &str) -> Result<TableMetadata> {
+    let response = client
+        .get_item()
+        .table_name(table_name)
+        .key("id", AttributeValue::N(METADATA_ITEM_ID.to_string()))
+        .consistent_read(true)
+        .send()
+        .await?;
+
+    let item = response.item.unwrap_or_default();
+    Ok(TableMetadata {
+        hash_key_id: get_string(&item, "hash_key_id"),
+        card_count: get_number(&item, "card_count"),
+        card_seed: get_number(&item, "card_seed"),
+        bin_fingerprint: get_string(&item, "bin_fingerprint"),
+        seed_status: get_string(&item, "seed_status"),
+        target_card_count: get_number(&item, "target_card_count"),
+        seeded_through: get_number(&item, "seeded_through"),
+    })
+}
+
+/// SET the given attributes (plus updated_at) and REMOVE `removals` on the metadata item
+pub async fn update_table_metadata(client: &DynamoDbClient, table_name: &str, assignments: Vec<(&str, AttributeValue)>, removals: &[&str]) -> Result<()> {
+    let mut request = client
+        .update_item()
+        .table_name(table_name)
+        .key("id", AttributeValue::N(METADATA_ITEM_ID.to_string()));
+
+    let mut set_clauses = vec!["updated_at = :updated_at".to_string()];
+    request = request.expression_attribute_values(":updated_at", AttributeValue::S(unix_timestamp()));
+    for (name, value) in assignments {
+        set_clauses.push(format!("{} = :{}", name, name));
+        request = request.expression_attribute_values(format!(":{}", name), value);
+    }
+
+    let mut update_expression = format!("SET {}", set_clauses.join(", "));
+    if !removals.is_empty() {
+        update_expression.push_str(&format!(" REMOVE {}", removals.join(", ")));
+    }
+
+    request.update_expression(update_expression).send().await?;
+    Ok(())
+}
+
+fn get_string(item: &HashMap<String, AttributeValue>, name: &str) -> Option<String> {
+    item.get(name).and_then(|v| v.as_s().ok()).cloned()
+}
+
+fn get_number(item: &HashMap<String, AttributeValue>, name: &str) -> Option<u64> {
+    item.get(name).and_then(|v| v.as_n().ok()).and_then(|n| n.parse().ok())
+}
+
+// Seconds since the Unix epoch
+fn unix_timestamp() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_else(|_| "0".to_string())
+}
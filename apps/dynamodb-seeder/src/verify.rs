@@ -0,0 +1,149 @@
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_dynamodb::types::{AttributeValue, KeysAndAttributes};
+use futures::stream::{self, StreamExt};
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::pan_generator::GeneratedCard;
+use crate::pan_hasher::PanHasher;
+use crate::table_metadata::get_table_metadata;
+use crate::{build_generator, SeedOptions};
+
+// DynamoDB BatchGetItem limit
+const BATCH_GET_SIZE: u64 = 100;
+// Example ids printed per problem category
+const MAX_REPORTED_IDS: usize = 20;
+
+#[derive(Default)]
+struct VerifyReport {
+    checked: u64,
+    missing: Vec<u64>,
+    mismatched: Vec<(u64, String)>,
+}
+
+/// Regenerate every card from the recorded seed and compare it with the stored item. Reports ids
+/// that are missing or whose attributes differ, and fails if there are any.
+pub async fn verify_dynamodb(client: &DynamoDbClient, table_name: &str, hasher: &PanHasher, options: &SeedOptions) -> Result<()> {
+    let metadata = get_table_metadata(client, table_name).await?;
+    let recorded_seed = metadata
+        .card_seed
+        .ok_or_else(|| anyhow::anyhow!("{} has no recorded card_seed; it was not seeded deterministically", table_name))?;
+    let (generator, seed) = build_generator(&options.card_brand, options.bin_table_path.as_deref(), options.card_seed, Some(recorded_seed))?;
+    if metadata.bin_fingerprint.as_deref() != Some(generator.fingerprint()) {
+        println!("⚠️ BIN table fingerprint differs from the one recorded at seeding time; expect mismatches");
+    }
+    let num_cards = metadata.target_card_count.or(metadata.card_count).unwrap_or(options.num_cards);
+
+    println!("Verifying {} cards in {} (seed {}, hash key {})...", num_cards, table_name, seed, hasher.key_id());
+
+    let results: Vec<Result<VerifyReport>> = stream::iter((0..num_cards).step_by(BATCH_GET_SIZE as usize))
+        .map(|chunk_start| {
+            let chunk_end = (chunk_start + BATCH_GET_SIZE).min(num_cards);
+            let expected = (chunk_start..chunk_end)
+                .map(|id| generator.card_for_id(id).map(|card| (id, card)))
+                .collect::<Result<Vec<_>>>();
+            async move { verify_chunk(client, table_name, hasher, expected?).await }
+        })
+        .buffer_unordered(options.concurrency)
+        .collect()
+        .await;
+
+    let mut report = VerifyReport::default();
+    for result in results {
+        let chunk = result?;
+        report.checked += chunk.checked;
+        report.missing.extend(chunk.missing);
+        report.mismatched.extend(chunk.mismatched);
+    }
+    report.missing.sort_unstable();
+    report.mismatched.sort_by_key(|(id, _)| *id);
+
+    println!("Checked {} ids: {} missing, {} mismatched", report.checked, report.missing.len(), report.mismatched.len());
+    if !report.missing.is_empty() {
+        println!("Missing ids (first {}): {:?}", MAX_REPORTED_IDS, &report.missing[..report.missing.len().min(MAX_REPORTED_IDS)]);
+    }
+    for (id, reason) in report.mismatched.iter().take(MAX_REPORTED_IDS) {
+        println!("Mismatch at id {}: {}", id, reason);
+    }
+
+    if report.missing.is_empty() && report.mismatched.is_empty() {
+        println!("✅ Verification passed");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Verification failed: {} missing, {} mismatched items", report.missing.len(), report.mismatched.len()))
+    }
+}
+
+async fn verify_chunk(client: &DynamoDbClient, table_name: &str, hasher: &PanHasher, expected: Vec<(u64, GeneratedCard)>) -> Result<VerifyReport> {
+    let mut pending_keys: Vec<HashMap<String, AttributeValue>> = expected
+        .iter()
+        .map(|(id, _)| HashMap::from([("id".to_string(), AttributeValue::N(id.to_string()))]))
+        .collect();
+    let mut stored: HashMap<u64, HashMap<String, AttributeValue>> = HashMap::new();
+
+    // Retry unprocessed keys until every requested id has been read
+    while !pending_keys.is_empty() {
+        let response = client
+            .batch_get_item()
+            .request_items(table_name, KeysAndAttributes::builder().set_keys(Some(pending_keys)).consistent_read(true).build()?)
+            .send()
+            .await?;
+
+        for item in response.responses().and_then(|r| r.get(table_name)).cloned().unwrap_or_default() {
+            if let Some(id) = item.get("id").and_then(|v| v.as_n().ok()).and_then(|n| n.parse::<u64>().ok()) {
+                stored.insert(id, item);
+            }
+        }
+        pending_keys = response
+            .unprocessed_keys()
+            .and_then(|unprocessed| unprocessed.get(table_name))
+            .map(|keys| keys.keys().to_vec())
+            .unwrap_or_default();
+        if !pending_keys.is_empty() {
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    let mut report = VerifyReport { checked: expected.len() as u64, ..Default::default() };
+    for (id, card) in expected {
+        match stored.get(&id) {
+            None => report.missing.push(id),
+            Some(item) => {
+                if let Some(reason) = compare_item(item, &card, hasher) {
+                    report.mismatched.push((id, reason));
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn compare_item(item: &HashMap<String, AttributeValue>, card: &GeneratedCard, hasher: &PanHasher) -> Option<String> {
+    let attribute = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).map(|s| s.as_str()).unwrap_or("");
+
+    let expected_fields = [
+        ("pan", card.pan.as_str()),
+        ("card_type", card.card_type.as_str()),
+        ("bin", card.bin.as_str()),
+        ("issuer", card.issuer.as_str()),
+        ("issuer_country", card.country.as_str()),
+    ];
+    let mut differences: Vec<String> = expected_fields
+        .iter()
+        .filter(|(name, expected)| attribute(name) != *expected)
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    // hash_pan can only be checked for items hashed with the key we were given
+    if attribute("hash_key_id") != hasher.key_id() {
+        differences.push(format!("hash_key_id ({} instead of {})", attribute("hash_key_id"), hasher.key_id()));
+    } else if attribute("hash_pan") != hasher.hash(&card.pan) {
+        differences.push("hash_pan".to_string());
+    }
+
+    if differences.is_empty() {
+        None
+    } else {
+        Some(format!("differs in {}", differences.join(", ")))
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::date_allocation::{DateAllocator, DateProfile};
+use crate::queues::split_by_weight;
+
+/// One brand of a mixed portfolio and its relative share of the jobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrandShare {
+    pub card_brand: String,
+    /// Defaults to the card brand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_brand: Option<String>,
+    pub weight: f64,
+}
+
+impl BrandShare {
+    pub fn network_brand(&self) -> &str {
+        self.network_brand.as_deref().unwrap_or(&self.card_brand)
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.card_brand, self.network_brand())
+    }
+}
+
+/// `CARD[/NETWORK]=WEIGHT`, e.g. `VISA=60` or `VISA/VISA=60`
+impl FromStr for BrandShare {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (brands, weight) = value
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected CARD[/NETWORK]=WEIGHT, got {:?}", value))?;
+        let weight = weight.trim().parse().map_err(|e| anyhow!("invalid weight in {:?}: {}", value, e))?;
+        let (card_brand, network_brand) = match brands.split_once('/') {
+            Some((card_brand, network_brand)) => (card_brand, Some(network_brand.trim().to_string())),
+            None => (brands, None),
+        };
+        Ok(Self { card_brand: card_brand.trim().to_string(), network_brand, weight })
+    }
+}
+
+/// The jobs of one brand: a contiguous range of job indexes whose dates span the whole window
+#[derive(Debug, Clone)]
+pub struct BrandSegment {
+    pub brand: BrandShare,
+    pub first_job_index: i32,
+    pub jobs: i32,
+    pub date_allocator: DateAllocator,
+    /// Subtracted from a job index before allocating its date. Block and weighted allocations
+    /// restart at the segment's first job; round-robin keeps the run-wide rotation so day totals
+    /// stay even.
+    pub date_job_index_offset: i32,
+}
+
+impl BrandSegment {
+    pub fn date_for_job(&self, job_index: i32) -> Result<NaiveDate> {
+        self.date_allocator.date_for_job(job_index - self.date_job_index_offset)
+    }
+
+    /// Brand and date settings for the segment's arrays
+    pub fn job_environment(&self) -> Result<Vec<(&'static str, String)>> {
+        let mut environment = vec![
+            ("CARD_BRAND", self.brand.card_brand.clone()),
+            ("NETWORK_BRAND", self.brand.network_brand().to_string()),
+            ("DATE_JOB_INDEX_OFFSET", self.date_job_index_offset.to_string()),
+        ];
+        environment.extend(self.date_allocator.job_environment()?);
+        Ok(environment)
+    }
+}
+
+/// Share the run's jobs between brands in proportion to their weights (largest remainder). A
+/// backfill splits its jobs per day instead, so the total stays `days × jobs_per_day`.
+pub fn split_brands(
+    brands: &[BrandShare],
+    start: NaiveDate,
+    end: NaiveDate,
+    total_jobs: i32,
+    backfill_jobs_per_day: Option<i32>,
+    profile: Option<&DateProfile>,
+) -> Result<Vec<BrandSegment>> {
+    let days = (end - start).num_days() as i32 + 1;
+    let weights: Vec<f64> = brands.iter().map(|brand| brand.weight).collect();
+    let shares = match (profile, backfill_jobs_per_day) {
+        (None, Some(jobs_per_day)) => split_by_weight(jobs_per_day, &weights),
+        _ => split_by_weight(total_jobs, &weights),
+    };
+
+    let mut segments = Vec::new();
+    let mut first_job_index = 0;
+    for (brand, share) in brands.iter().zip(shares) {
+        if share == 0 {
+            eprintln!("⚠️ Brand {} gets no jobs at this run size", brand.label());
+            continue;
+        }
+        let (jobs, date_allocator, date_job_index_offset) = match (profile, backfill_jobs_per_day) {
+            (Some(profile), _) => (share, DateAllocator::weighted(start, end, share, profile.clone())?, first_job_index),
+            (None, Some(_)) => (share * days, DateAllocator::Block { start, end, jobs_per_day: share }, first_job_index),
+            (None, None) => (share, DateAllocator::RoundRobin { start, end }, 0),
+        };
+        segments.push(BrandSegment { brand: brand.clone(), first_job_index, jobs, date_allocator, date_job_index_offset });
+        first_job_index += jobs;
+    }
+    if segments.is_empty() {
+        bail!("No brand of the mix gets a job; raise the target size or jobs per day");
+    }
+    Ok(segments)
+}
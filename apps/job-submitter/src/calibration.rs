@@ -0,0 +1,99 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::plan::TableCounts;
+
+// Measured with `GENERATOR_MODE=calibrate data-generator`; used when --calibration is not given
+const DEFAULT_CALIBRATION: &str = include_str!("../calibration.json");
+
+/// A per-row measurement for each of the six tables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerRow {
+    pub authorization: f64,
+    pub authorization_hash: f64,
+    pub clearing: f64,
+    pub clearing_hash: f64,
+    pub chargeback: f64,
+    pub chargeback_hash: f64,
+}
+
+/// Output of the data generator's calibration mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calibration {
+    pub sample_rows: u64,
+    pub compression: String,
+    pub created_at: String,
+    /// Encoded Parquet bytes per row
+    pub bytes_per_row: PerRow,
+    /// Single-thread seconds to generate and encode a row; missing from older calibrations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds_per_row: Option<PerRow>,
+}
+
+impl Calibration {
+    /// Load a calibration file, or the bundled measurement when no path is given
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let content = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read calibration {}: {}", path, e))?,
+            None => DEFAULT_CALIBRATION.to_string(),
+        };
+        let mut calibration: Calibration = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid calibration {}: {}", path.unwrap_or("calibration.json"), e))?;
+
+        let bytes_per_row = &calibration.bytes_per_row;
+        let sizes = [
+            bytes_per_row.authorization,
+            bytes_per_row.authorization_hash,
+            bytes_per_row.clearing,
+            bytes_per_row.clearing_hash,
+            bytes_per_row.chargeback,
+            bytes_per_row.chargeback_hash,
+        ];
+        if sizes.iter().any(|size| !size.is_finite() || *size <= 0.0) {
+            bail!("Calibration {}: bytes_per_row must be positive for every table", path.unwrap_or("calibration.json"));
+        }
+        if calibration.seconds_per_row.is_none() {
+            eprintln!("⚠️ Calibration {} has no seconds_per_row; estimating compute with the bundled timing", path.unwrap_or("calibration.json"));
+            calibration.seconds_per_row = Self::load(None)?.seconds_per_row;
+        }
+        Ok(calibration)
+    }
+
+    /// Encoded size of the given rows across all six tables
+    pub fn bytes_for(&self, rows: &TableCounts) -> f64 {
+        let bytes_per_row = &self.bytes_per_row;
+        rows.authorization as f64 * bytes_per_row.authorization
+            + rows.authorization_hash as f64 * bytes_per_row.authorization_hash
+            + rows.clearing as f64 * bytes_per_row.clearing
+            + rows.clearing_hash as f64 * bytes_per_row.clearing_hash
+            + rows.chargeback as f64 * bytes_per_row.chargeback
+            + rows.chargeback_hash as f64 * bytes_per_row.chargeback_hash
+    }
+
+    /// Single-thread time to generate and encode the given rows
+    pub fn seconds_for(&self, rows: &TableCounts) -> f64 {
+        let Some(seconds_per_row) = &self.seconds_per_row else {
+            return 0.0;
+        };
+        rows.authorization as f64 * seconds_per_row.authorization
+            + rows.authorization_hash as f64 * seconds_per_row.authorization_hash
+            + rows.clearing as f64 * seconds_per_row.clearing
+            + rows.clearing_hash as f64 * seconds_per_row.clearing_hash
+            + rows.chargeback as f64 * seconds_per_row.chargeback
+            + rows.chargeback_hash as f64 * seconds_per_row.chargeback_hash
+    }
+
+    /// Encoded size of each table for the given rows
+    pub fn bytes_per_table(&self, rows: &TableCounts) -> TableCounts {
+        let bytes_per_row = &self.bytes_per_row;
+        TableCounts {
+            authorization: (rows.authorization as f64 * bytes_per_row.authorization) as u64,
+            authorization_hash: (rows.authorization_hash as f64 * bytes_per_row.authorization_hash) as u64,
+            clearing: (rows.clearing as f64 * bytes_per_row.clearing) as u64,
+            clearing_hash: (rows.clearing_hash as f64 * bytes_per_row.clearing_hash) as u64,
+            chargeback: (rows.chargeback as f64 * bytes_per_row.chargeback) as u64,
+            chargeback_hash: (rows.chargeback_hash as f64 * bytes_per_row.chargeback_hash) as u64,
+        }
+    }
+}
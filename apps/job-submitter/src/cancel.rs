@@ -0,0 +1,87 @@
+use anyhow::{anyhow, bail, Result};
+use aws_sdk_batch::Client as BatchClient;
+use std::time::Duration;
+
+use crate::manifest::RunManifest;
+
+// DescribeJobs accepts at most 100 job IDs per call
+const DESCRIBE_CHUNK_SIZE: usize = 100;
+
+/// Every Batch job of a run as (label, job ID): the arrays of each attempt, then the steps.
+/// Acting on an array job acts on all of its children.
+fn run_jobs(manifest: &RunManifest) -> Vec<(String, String)> {
+    let arrays = manifest.all_arrays().map(|array| (array.job_name.clone(), array.job_id.clone()));
+    let steps = manifest.steps.iter().map(|step| (format!("step {}", step.name), step.job_id.clone()));
+    arrays.chain(steps).collect()
+}
+
+/// Cancel every job of a run that has not started yet, or with `terminate` also stop the running
+/// ones. Tries every job before reporting failures.
+pub async fn cancel_run(client: &BatchClient, manifest: &RunManifest, terminate: bool, reason: &str) -> Result<()> {
+    let jobs = run_jobs(manifest);
+    let action = if terminate { "Terminating" } else { "Cancelling" };
+    println!("🛑 {} {} jobs of run {}", action, jobs.len(), manifest.run_id);
+
+    let mut failed = 0;
+    for (label, job_id) in &jobs {
+        let result = if terminate {
+            client.terminate_job().job_id(job_id).reason(reason).send().await.map(|_| ())
+        } else {
+            client.cancel_job().job_id(job_id).reason(reason).send().await.map(|_| ())
+        };
+        match result {
+            Ok(()) => println!("   {} ({})", label, job_id),
+            Err(e) => {
+                eprintln!("❌ Failed to stop {} ({}): {:?}", label, job_id, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} jobs of run {} could not be stopped", failed, jobs.len(), manifest.run_id);
+    }
+    if !terminate {
+        println!("ℹ️  Jobs already running finish on their own; pass --terminate to stop them too");
+    }
+    Ok(())
+}
+
+/// Jobs of a run that have not finished yet, as (label, status). Jobs Batch no longer knows,
+/// about 7 days after they finished, count as finished.
+pub async fn unfinished_jobs(client: &BatchClient, manifest: &RunManifest) -> Result<Vec<(String, String)>> {
+    let jobs = run_jobs(manifest);
+    let mut unfinished = Vec::new();
+    for chunk in jobs.chunks(DESCRIBE_CHUNK_SIZE) {
+        let response = client
+            .describe_jobs()
+            .set_jobs(Some(chunk.iter().map(|(_, job_id)| job_id.clone()).collect()))
+            .send()
+            .await
+            .map_err(|e| anyhow!("DescribeJobs failed: {:?}", e))?;
+        for job in response.jobs() {
+            let status = job.status().map(|status| status.as_str().to_string()).unwrap_or_default();
+            if status != "SUCCEEDED" && status != "FAILED" {
+                let label = chunk
+                    .iter()
+                    .find(|(_, job_id)| job.job_id() == Some(job_id.as_str()))
+                    .map(|(label, _)| label.clone())
+                    .unwrap_or_default();
+                unfinished.push((label, status));
+            }
+        }
+    }
+    Ok(unfinished)
+}
+
+/// Poll until every job of the run has finished, so nothing writes files after a purge
+pub async fn wait_until_finished(client: &BatchClient, manifest: &RunManifest, interval: Duration) -> Result<()> {
+    loop {
+        let unfinished = unfinished_jobs(client, manifest).await?;
+        if unfinished.is_empty() {
+            return Ok(());
+        }
+        let jobs: Vec<String> = unfinished.iter().map(|(label, status)| format!("{} {}", label, status)).collect();
+        println!("⏳ [{}] Waiting for {} jobs to stop: {}", chrono::Utc::now().format("%H:%M:%S"), unfinished.len(), jobs.join(", "));
+        tokio::time::sleep(interval).await;
+    }
+}
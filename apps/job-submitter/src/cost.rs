@@ -0,0 +1,140 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+// List prices used when --prices is not given
+const DEFAULT_PRICES: &str = include_str!("../prices.json");
+
+// DynamoDB calls of one job: each thread reads the card table's metadata item and 1000 random
+// cards; the job reads its partition counter once and writes it three times (counter, claim,
+// release)
+const CARD_READS_PER_THREAD: u64 = 1001;
+const COUNTER_READS_PER_JOB: u64 = 1;
+const COUNTER_WRITES_PER_JOB: u64 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FargatePrices {
+    pub vcpu_hour: f64,
+    pub gb_hour: f64,
+    /// Fargate bills at least this long per task
+    pub minimum_billed_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3Prices {
+    pub put_per_1000: f64,
+    pub storage_gb_month: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DynamoDbPrices {
+    pub read_request_per_million: f64,
+    pub write_request_per_million: f64,
+}
+
+/// Offline price list, in `currency`, for the region the run is priced in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceTable {
+    pub currency: String,
+    pub region: String,
+    /// When the prices were last checked
+    pub updated: String,
+    pub fargate: FargatePrices,
+    pub s3: S3Prices,
+    pub dynamodb: DynamoDbPrices,
+}
+
+impl PriceTable {
+    /// Load a price table, or the bundled one when no path is given
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let content = match path {
+            Some(path) => std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read price table {}: {}", path, e))?,
+            None => DEFAULT_PRICES.to_string(),
+        };
+        let path = path.unwrap_or("prices.json");
+        let prices: PriceTable = serde_json::from_str(&content).map_err(|e| anyhow!("Invalid price table {}: {}", path, e))?;
+        let all = [
+            prices.fargate.vcpu_hour,
+            prices.fargate.gb_hour,
+            prices.fargate.minimum_billed_seconds,
+            prices.s3.put_per_1000,
+            prices.s3.storage_gb_month,
+            prices.dynamodb.read_request_per_million,
+            prices.dynamodb.write_request_per_million,
+        ];
+        if all.iter().any(|price| !price.is_finite() || *price < 0.0) {
+            bail!("Price table {}: prices must be non-negative numbers", path);
+        }
+        Ok(prices)
+    }
+}
+
+/// What one generator job uses
+pub struct JobUsage {
+    pub vcpus: i32,
+    pub memory_gb: i32,
+    pub threads: i32,
+    /// Wall time of a job: its threads run side by side, one per vCPU
+    pub seconds: f64,
+    /// Parquet files a thread writes, one per enabled table and specialized copy
+    pub uploads_per_thread: u64,
+}
+
+/// Usage and cost of a run, priced at on-demand Fargate rates
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    pub currency: String,
+    pub vcpu_hours: f64,
+    pub memory_gb_hours: f64,
+    pub s3_puts: u64,
+    pub storage_gb_months: f64,
+    pub dynamodb_reads: u64,
+    pub dynamodb_writes: u64,
+    pub compute_cost: f64,
+    pub s3_put_cost: f64,
+    /// One month of storing everything the run writes
+    pub storage_cost: f64,
+    pub dynamodb_cost: f64,
+    pub total_cost: f64,
+}
+
+impl CostEstimate {
+    pub fn new(prices: &PriceTable, jobs: i32, job: &JobUsage, stored_bytes: u64) -> Self {
+        let jobs = jobs as u64;
+        let task_hours = jobs as f64 * job.seconds.max(prices.fargate.minimum_billed_seconds) / 3600.0;
+        let vcpu_hours = task_hours * job.vcpus as f64;
+        let memory_gb_hours = task_hours * job.memory_gb as f64;
+        let s3_puts = jobs * job.threads as u64 * job.uploads_per_thread;
+        let storage_gb_months = stored_bytes as f64 / 1e9;
+        let dynamodb_reads = jobs * (job.threads as u64 * CARD_READS_PER_THREAD + COUNTER_READS_PER_JOB);
+        let dynamodb_writes = jobs * COUNTER_WRITES_PER_JOB;
+
+        let compute_cost = vcpu_hours * prices.fargate.vcpu_hour + memory_gb_hours * prices.fargate.gb_hour;
+        let s3_put_cost = s3_puts as f64 / 1000.0 * prices.s3.put_per_1000;
+        let storage_cost = storage_gb_months * prices.s3.storage_gb_month;
+        let dynamodb_cost = dynamodb_reads as f64 / 1e6 * prices.dynamodb.read_request_per_million
+            + dynamodb_writes as f64 / 1e6 * prices.dynamodb.write_request_per_million;
+        Self {
+            currency: prices.currency.clone(),
+            vcpu_hours,
+            memory_gb_hours,
+            s3_puts,
+            storage_gb_months,
+            dynamodb_reads,
+            dynamodb_writes,
+            compute_cost,
+            s3_put_cost,
+            storage_cost,
+            dynamodb_cost,
+            total_cost: compute_cost + s3_put_cost + storage_cost + dynamodb_cost,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Estimated cost: {:.2} {} (compute {:.2}, S3 PUTs {:.2}, storage {:.2}/month, DynamoDB {:.2})", self.total_cost, self.currency, self.compute_cost, self.s3_put_cost, self.storage_cost, self.dynamodb_cost);
+        println!("Estimated usage: {:.1} vCPU-hours, {:.1} GB-hours, {} S3 PUTs, {:.1} GB-months stored, {} DynamoDB reads + {} writes", self.vcpu_hours, self.memory_gb_hours, self.s3_puts, self.storage_gb_months, self.dynamodb_reads, self.dynamodb_writes);
+    }
+}
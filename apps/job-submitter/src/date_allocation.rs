@@ -0,0 +1,185 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+// Profile weights are compared as integers so every toolchain allocates identically
+const WEIGHT_SCALE: f64 = 1000.0;
+
+/// Relative job volume by weekday (Monday first) and by month (January first)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateProfile {
+    pub day_of_week: [f64; 7],
+    pub month: [f64; 12],
+}
+
+impl DateProfile {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read date profile {}: {}", path, e))?;
+        let profile: DateProfile = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid date profile {}: {}", path, e))?;
+        if profile.day_of_week.iter().chain(profile.month.iter()).any(|w| !w.is_finite() || *w < 0.0) {
+            bail!("Date profile {}: weights must be non-negative numbers", path);
+        }
+        Ok(profile)
+    }
+
+    fn day_weight(&self, date: NaiveDate) -> u64 {
+        let scale = |weight: f64| (weight * WEIGHT_SCALE).round() as u64;
+        scale(self.day_of_week[date.weekday().num_days_from_monday() as usize]) * scale(self.month[date.month0() as usize])
+    }
+}
+
+/// Maps a job index onto a partition date. The generator implements the same schemes, and the
+/// submitter passes the scheme and window to every job so both sides agree without a clock.
+#[derive(Debug, Clone)]
+pub enum DateAllocator {
+    /// Job i lands on start + i / jobs_per_day: contiguous, exactly jobs_per_day jobs per day
+    Block { start: NaiveDate, end: NaiveDate, jobs_per_day: i32 },
+    /// Job i lands on start + i % days: every day gets total / days jobs, give or take one
+    RoundRobin { start: NaiveDate, end: NaiveDate },
+    /// Days get jobs in proportion to their profile weight (largest remainder), in date order
+    Weighted { start: NaiveDate, end: NaiveDate, total_jobs: i32, profile: DateProfile, cumulative_jobs: Vec<u64> },
+}
+
+impl DateAllocator {
+    pub fn weighted(start: NaiveDate, end: NaiveDate, total_jobs: i32, profile: DateProfile) -> Result<Self> {
+        let weights: Vec<u64> = start.iter_days().take_while(|date| *date <= end).map(|date| profile.day_weight(date)).collect();
+        let total_weight: u128 = weights.iter().map(|w| *w as u128).sum();
+        if total_weight == 0 {
+            bail!("Date profile gives every day between {} and {} a weight of 0", start, end);
+        }
+
+        let total = total_jobs as u128;
+        let mut counts: Vec<u64> = weights.iter().map(|w| (total * *w as u128 / total_weight) as u64).collect();
+        let mut remainders: Vec<(u128, usize)> = weights.iter().enumerate().map(|(day, w)| (total * *w as u128 % total_weight, day)).collect();
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        let unassigned = total_jobs as u64 - counts.iter().sum::<u64>();
+        for (_, day) in remainders.iter().take(unassigned as usize) {
+            counts[*day] += 1;
+        }
+
+        let cumulative_jobs = counts
+            .iter()
+            .scan(0, |sum, count| {
+                *sum += count;
+                Some(*sum)
+            })
+            .collect();
+        Ok(DateAllocator::Weighted { start, end, total_jobs, profile, cumulative_jobs })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DateAllocator::Block { .. } => "block",
+            DateAllocator::RoundRobin { .. } => "round_robin",
+            DateAllocator::Weighted { .. } => "weighted",
+        }
+    }
+
+    pub fn window(&self) -> (NaiveDate, NaiveDate) {
+        match self {
+            DateAllocator::Block { start, end, .. }
+            | DateAllocator::RoundRobin { start, end }
+            | DateAllocator::Weighted { start, end, .. } => (*start, *end),
+        }
+    }
+
+    pub fn date_for_job(&self, job_index: i32) -> Result<NaiveDate> {
+        let (start, end) = self.window();
+        let day = match self {
+            DateAllocator::Block { jobs_per_day, .. } => (job_index / jobs_per_day) as i64,
+            DateAllocator::RoundRobin { .. } => (job_index as i64) % ((end - start).num_days() + 1),
+            DateAllocator::Weighted { cumulative_jobs, .. } => {
+                cumulative_jobs.partition_point(|jobs| *jobs <= job_index as u64) as i64
+            }
+        };
+        let date = start + chrono::Duration::days(day);
+        if date > end {
+            bail!("Job {} falls after the end of the {} to {} window", job_index, start, end);
+        }
+        Ok(date)
+    }
+
+    /// Environment passed to generator jobs so they place themselves without reading the clock
+    pub fn job_environment(&self) -> Result<Vec<(&'static str, String)>> {
+        let (start, end) = self.window();
+        let mut environment = vec![
+            ("PARTITION_START_DATE", start.format("%Y-%m-%d").to_string()),
+            ("PARTITION_END_DATE", end.format("%Y-%m-%d").to_string()),
+            ("DATE_ALLOCATION", self.name().to_string()),
+        ];
+        match self {
+            DateAllocator::Block { jobs_per_day, .. } => environment.push(("JOBS_PER_DAY", jobs_per_day.to_string())),
+            DateAllocator::RoundRobin { .. } => {}
+            DateAllocator::Weighted { total_jobs, profile, .. } => {
+                environment.push(("TOTAL_JOBS", total_jobs.to_string()));
+                environment.push(("DATE_PROFILE", serde_json::to_string(profile)?));
+            }
+        }
+        Ok(environment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The generator's partition_date.rs pins the same tables; a change to either allocator must
+    // keep both passing, or dry-run plans stop matching what jobs write.
+    // Jobs 0..=21 over Monday 2024-01-29 to Sunday 2024-02-04; None is a job past the window
+    const BLOCK_3_PER_DAY: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-29"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-30"), Some("2024-01-30"),
+        Some("2024-01-31"), Some("2024-01-31"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-01"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"),
+        Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), None,
+    ];
+    const ROUND_ROBIN: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-02"), Some("2024-02-03"),
+        Some("2024-02-04"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-02"),
+        Some("2024-02-03"), Some("2024-02-04"), Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-04"), Some("2024-01-29"),
+    ];
+    // 20 jobs, weekends and February weighted up: day weights 1,1,1,2,2,4,6 floor to 1,1,1,2,2,4,7
+    // and the two leftover jobs go to the largest remainders, Saturday then Thursday
+    const WEIGHTED_20: [Option<&str>; 22] = [
+        Some("2024-01-29"), Some("2024-01-30"), Some("2024-01-31"), Some("2024-02-01"), Some("2024-02-01"), Some("2024-02-01"),
+        Some("2024-02-02"), Some("2024-02-02"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"), Some("2024-02-03"),
+        Some("2024-02-03"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"), Some("2024-02-04"),
+        Some("2024-02-04"), Some("2024-02-04"), None, None,
+    ];
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn profile() -> DateProfile {
+        DateProfile { day_of_week: [1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 3.0], month: [1.0, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0] }
+    }
+
+    fn assert_allocates(allocator: &DateAllocator, expected: &[Option<&str>]) {
+        for (job_index, expected) in expected.iter().enumerate() {
+            let actual = allocator.date_for_job(job_index as i32).ok();
+            assert_eq!(actual, expected.map(date), "job {}", job_index);
+        }
+    }
+
+    #[test]
+    fn block_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::Block { start: date("2024-01-29"), end: date("2024-02-04"), jobs_per_day: 3 };
+        assert_allocates(&allocator, &BLOCK_3_PER_DAY);
+    }
+
+    #[test]
+    fn round_robin_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::RoundRobin { start: date("2024-01-29"), end: date("2024-02-04") };
+        assert_allocates(&allocator, &ROUND_ROBIN);
+    }
+
+    #[test]
+    fn weighted_allocation_matches_pinned_table() {
+        let allocator = DateAllocator::weighted(date("2024-01-29"), date("2024-02-04"), 20, profile()).unwrap();
+        assert_allocates(&allocator, &WEIGHTED_20);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client as S3Client;
+
+use crate::manifest::RunManifest;
+
+/// Where run records are kept: a local directory or `s3://bucket/prefix`, one `<run_id>.json` per run
+pub enum Ledger {
+    Local(std::path::PathBuf),
+    S3 { client: S3Client, bucket: String, prefix: String },
+}
+
+impl Ledger {
+    pub async fn open(location: &str) -> Result<Self> {
+        match location.strip_prefix("s3://") {
+            Some(location) => {
+                let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Ok(Ledger::S3 {
+                    client: S3Client::new(&config),
+                    bucket: bucket.to_string(),
+                    prefix: prefix.trim_end_matches('/').to_string(),
+                })
+            }
+            None => Ok(Ledger::Local(std::path::PathBuf::from(location))),
+        }
+    }
+
+    fn key(prefix: &str, run_id: &str) -> String {
+        if prefix.is_empty() {
+            format!("{}.json", run_id)
+        } else {
+            format!("{}/{}.json", prefix, run_id)
+        }
+    }
+
+    pub async fn save(&self, manifest: &RunManifest) -> Result<()> {
+        let body = serde_json::to_vec_pretty(manifest)?;
+        match self {
+            Ledger::Local(dir) => {
+                std::fs::create_dir_all(dir)?;
+                let path = dir.join(format!("{}.json", manifest.run_id));
+                std::fs::write(&path, body).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+            }
+            Ledger::S3 { client, bucket, prefix } => {
+                let key = Self::key(prefix, &manifest.run_id);
+                client
+                    .put_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .content_type("application/json")
+                    .body(ByteStream::from(body))
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Failed to write s3://{}/{}: {:?}", bucket, key, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn load(&self, run_id: &str) -> Result<RunManifest> {
+        let content = match self {
+            Ledger::Local(dir) => {
+                let path = dir.join(format!("{}.json", run_id));
+                std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?
+            }
+            Ledger::S3 { client, bucket, prefix } => {
+                let key = Self::key(prefix, run_id);
+                let object = client
+                    .get_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Failed to read s3://{}/{}: {:?}", bucket, key, e))?;
+                object.body.collect().await?.into_bytes().to_vec()
+            }
+        };
+        serde_json::from_slice(&content).map_err(|e| anyhow!("Invalid ledger record for run {}: {}", run_id, e))
+    }
+}
+
+/// Write the run record to its local manifest (when there is one) and to its ledger
+pub async fn record(manifest: &RunManifest, manifest_path: Option<&str>) -> Result<()> {
+    if let Some(path) = manifest_path {
+        manifest.save(path)?;
+    }
+    if let Some(location) = &manifest.ledger {
+        Ledger::open(location).await?.save(manifest).await?;
+    }
+    Ok(())
+}
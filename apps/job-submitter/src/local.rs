@@ -0,0 +1,143 @@
+use anyhow::{anyhow, bail, Result};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::process::Command;
+
+use crate::manifest::{SubmittedArray, SubmittedStep};
+use crate::steps::StepSpec;
+
+/// How one generator subprocess ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalJobResult {
+    pub job_index: i32,
+    /// None when the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub log: String,
+    pub elapsed_secs: f64,
+}
+
+/// Runs the children of planned arrays as data-generator subprocesses instead of Batch jobs, and
+/// the run's steps as their local binaries
+pub struct LocalExecutor {
+    pub generator_bin: PathBuf,
+    pub parallelism: usize,
+    pub log_dir: PathBuf,
+}
+
+impl LocalExecutor {
+    /// Run every child of `arrays` with the environment Batch would give it, at most
+    /// `parallelism` at a time. Returns the jobs that exited non-zero.
+    pub async fn run(&self, arrays: &[SubmittedArray]) -> Result<Vec<LocalJobResult>> {
+        if !self.generator_bin.is_file() {
+            bail!("data-generator binary {} not found; build it with `cargo build --release` in apps/data-generator or pass --generator-bin", self.generator_bin.display());
+        }
+        self.create_log_dir()?;
+
+        let jobs: Vec<(&SubmittedArray, i32)> = arrays
+            .iter()
+            .flat_map(|array| (0..array.size).map(move |array_index| (array, array_index)))
+            .collect();
+        let total = jobs.len();
+        println!("🖥️ Running {} jobs locally, {} at a time (logs in {})", total, self.parallelism, self.log_dir.display());
+
+        let mut results = stream::iter(jobs)
+            .map(|(array, array_index)| self.run_job(array, array_index))
+            .buffer_unordered(self.parallelism);
+        let mut finished = 0;
+        let mut failures = Vec::new();
+        while let Some(result) = results.next().await {
+            let result = result?;
+            finished += 1;
+            match result.exit_code {
+                Some(0) => println!("✅ [{}/{}] Job {} finished in {:.1}s", finished, total, result.job_index, result.elapsed_secs),
+                exit_code => {
+                    eprintln!("❌ [{}/{}] Job {} ended with {} after {:.1}s (log: {})", finished, total, result.job_index, describe_exit(exit_code), result.elapsed_secs, result.log);
+                    failures.push(result);
+                }
+            }
+        }
+        failures.sort_by_key(|result| result.job_index);
+        Ok(failures)
+    }
+
+    /// Run a seed or validate step with `environment` and wait for it
+    pub async fn run_step(&self, run_id: &str, name: &str, step: &StepSpec, environment: &BTreeMap<String, String>) -> Result<SubmittedStep> {
+        let binary = step.binary.as_deref().unwrap_or_default();
+        println!("🔗 Running step {}: {} {}", name, binary, step.args.join(" "));
+        self.create_log_dir()?;
+        let log = self.log_dir.join(format!("{}.log", name));
+        let (exit_code, elapsed_secs) = self.run_process(Path::new(binary), &step.args, environment, &[], &log).await?;
+        match exit_code {
+            Some(0) => println!("✅ Step {} finished in {:.1}s", name, elapsed_secs),
+            _ => eprintln!("❌ Step {} ended with {} after {:.1}s (log: {})", name, describe_exit(exit_code), elapsed_secs, log.display()),
+        }
+        Ok(SubmittedStep {
+            name: name.to_string(),
+            job_id: format!("local-{}-{}", run_id, name),
+            job_name: format!("6table-multiple-buckets-{}-{}", run_id, name),
+            queue: "local".to_string(),
+            depends_on: Vec::new(),
+            exit_code,
+        })
+    }
+
+    async fn run_job(&self, array: &SubmittedArray, array_index: i32) -> Result<LocalJobResult> {
+        let job_index = array.job_index_offset + array_index;
+        let log = self.log_dir.join(format!("job_{}.log", job_index));
+        // Set by Batch for every array child; the child job ID keys the partition order claim
+        let batch_environment = [
+            ("AWS_BATCH_JOB_ARRAY_INDEX", array_index.to_string()),
+            ("AWS_BATCH_JOB_ID", format!("{}:{}", array.job_id, array_index)),
+        ];
+        let (exit_code, elapsed_secs) = self.run_process(&self.generator_bin, &[], &array.environment, &batch_environment, &log).await?;
+        Ok(LocalJobResult {
+            job_index,
+            exit_code,
+            log: log.display().to_string(),
+            elapsed_secs,
+        })
+    }
+
+    fn create_log_dir(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.log_dir).map_err(|e| anyhow!("Failed to create {}: {}", self.log_dir.display(), e))
+    }
+
+    /// Run `program` to completion with its output in `log`. Returns the exit code (None when
+    /// killed by a signal) and the elapsed seconds.
+    async fn run_process(
+        &self,
+        program: &Path,
+        args: &[String],
+        environment: &BTreeMap<String, String>,
+        extra_environment: &[(&str, String)],
+        log: &Path,
+    ) -> Result<(Option<i32>, f64)> {
+        let log_file = std::fs::File::create(log).map_err(|e| anyhow!("Failed to create {}: {}", log.display(), e))?;
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .envs(environment)
+            .envs(extra_environment.iter().map(|(name, value)| (*name, value)))
+            .stdin(Stdio::null())
+            .stdout(log_file.try_clone()?)
+            .stderr(log_file);
+        if std::env::var_os("RUST_LOG").is_none() {
+            command.env("RUST_LOG", "info");
+        }
+
+        let started = Instant::now();
+        let status = command
+            .status()
+            .await
+            .map_err(|e| anyhow!("Failed to start {}: {}", program.display(), e))?;
+        Ok((status.code(), started.elapsed().as_secs_f64()))
+    }
+}
+
+fn describe_exit(exit_code: Option<i32>) -> String {
+    exit_code.map(|code| format!("exit code {}", code)).unwrap_or_else(|| "a signal".to_string())
+}
@@ -1,79 +1,396 @@
 use aws_sdk_batch::Client as BatchClient;
-use aws_sdk_batch::types::{ContainerOverrides, KeyValuePair};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use futures::stream::{self, StreamExt};
-use std::time::Instant;
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Result};
+use serde::Serialize;
 
-const MAX_ARRAY_SIZE: i32 = 1000;
-const NUM_OF_ROWS: i32 = 1000000;
-const CONCURRENT_SUBMISSIONS: usize = 50;
-const BASE_TB: i32 = 655; 
+mod brand_mix;
+mod calibration;
+mod cancel;
+mod cost;
+mod date_allocation;
+mod ledger;
+mod local;
+mod manifest;
+mod plan;
+mod purge;
+mod queues;
+mod retry;
+mod spec;
+mod steps;
+mod submission;
+mod watch;
 
-// Base configuration
-fn get_base_jobs() -> i32 {
-    let threads_per_job: i32 = 3; // 3 threads per job
-    1_107_000 / threads_per_job
-}
+use calibration::Calibration;
+use date_allocation::DateProfile;
+use manifest::{Executor, RunConfig, RunManifest, RunStatus, SubmittedArray};
+use plan::{build_plan, format_bytes, print_plan, ArrayJobPlan, PlanSettings, TableCounts};
+use spec::RunSpec;
+use steps::StepSpec;
+
+const CONCURRENT_SUBMISSIONS: usize = 50;
+const BYTES_PER_TB: f64 = 1e12;
 
-fn calculate_job_parameters(target_tb: i32) -> (i32, i32) {
-    // Calculate total jobs needed based on target TB
-    let base_jobs = get_base_jobs();
-    let total_jobs = (base_jobs as f64 * target_tb as f64 / BASE_TB as f64) as i32;
+fn calculate_job_parameters(target_tb: i32, bytes_per_job: f64, max_array_size: i32) -> (i32, i32) {
+    // Calculate total jobs needed to reach target TB at the calibrated job size
+    let total_jobs = (target_tb as f64 * BYTES_PER_TB / bytes_per_job).ceil() as i32;
     
     // Always use max array size 
-    // (will be split into multiple arrays of size MAX_ARRAY_SIZE each)
-    let array_size = MAX_ARRAY_SIZE;
+    // (will be split into multiple arrays of size max_array_size each)
+    let array_size = max_array_size;
     
     (total_jobs, array_size)
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    submit: Args,
+}
+
+// Without a subcommand these flags submit a run
+#[derive(clap::Args, Debug)]
 struct Args {
+    /// Run spec (YAML, or JSON for .json) with any of the settings below plus per-table options
+    #[arg(long)]
+    spec: Option<String>,
+    /// Override a spec field, e.g. --set tables.clearing_hash.enabled=false; repeatable
+    #[arg(long, value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    #[command(flatten)]
+    settings: SpecArgs,
+    /// Print the planned array jobs and totals without contacting AWS
     #[arg(long)]
-    spot_queue_name: String,
+    dry_run: bool,
+    /// Dry-run output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Where to record the submitted array job IDs for `watch`
+    #[arg(long, default_value = "run-manifest.json")]
+    manifest: String,
+    /// Run ID passed to every job as RUN_ID and tagged on every Batch job; generated when omitted
+    #[arg(long)]
+    run_id: Option<String>,
+    /// Run ledger, a directory or s3://bucket/prefix; defaults to s3://<payment data bucket>/runs
+    #[arg(long)]
+    ledger: Option<String>,
+    /// Watch the run after submitting and exit non-zero if any job fails
+    #[arg(long)]
+    wait: bool,
+    /// Run the jobs as data-generator subprocesses on this machine, writing to --local-output instead of S3
+    #[arg(long, conflicts_with = "wait")]
+    local: bool,
+    /// data-generator binary for --local
+    #[arg(long, default_value = "../data-generator/target/release/data-generator")]
+    generator_bin: String,
+    /// Jobs run at once with --local; defaults to one per 4 CPUs
+    #[arg(long)]
+    local_parallelism: Option<usize>,
+    /// Directory for --local output: generated files under data/, one log per job under logs/,
+    /// OpenMetrics files per job under metrics/
+    #[arg(long, default_value = "local-run")]
+    local_output: String,
+    /// Seconds between status polls with --wait
+    #[arg(long, default_value_t = 60)]
+    poll_interval: u64,
+}
+
+/// Flags that override the run spec field of the same name
+#[derive(clap::Args, Debug, Serialize)]
+struct SpecArgs {
     #[arg(long)]
-    ondemand_queue_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spot_queue_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ondemand_queue_name: Option<String>,
+    /// Queues to share jobs between instead of the spot/on-demand pair, e.g. spot-a=80,ondemand=20
+    #[arg(long = "queue", value_delimiter = ',', value_name = "NAME[=WEIGHT]")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queues: Option<Vec<queues::QueueSpec>>,
+    /// weighted shares jobs by queue weight; priority fills queues in order up to their max_jobs
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_distribution: Option<queues::QueueDistribution>,
+    /// Resubmit arrays whose submission fails to this queue, e.g. the on-demand queue
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fallback_queue: Option<String>,
     #[arg(short, long)]
-    job_definition: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_definition: Option<String>,
     #[arg(short, long)]
-    payment_data_bucket_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payment_data_bucket_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clearing_bucket_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authorization_bucket_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chargeback_bucket_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash_pan_table_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partition_counter_table_name: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    card_brand: Option<String>,
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_brand: Option<String>,
+    /// Split jobs between brands by weight, e.g. VISA=60,MASTERCARD=30,AMEX=10 (CARD/NETWORK=WEIGHT for another network)
+    #[arg(long, value_delimiter = ',', value_name = "CARD[/NETWORK]=WEIGHT")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brand_mix: Option<Vec<brand_mix::BrandShare>>,
+    /// Total data volume in TB (env TARGET_TB)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_tb: Option<i32>,
+    /// true for the historical load, false for the nightly window (env INITIAL_LOAD)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    initial_load: Option<bool>,
+    /// Chargeback rate in percent, 0.1 = 0.1% (env CHARGEBACK_PERCENTAGE)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chargeback_percentage: Option<f64>,
+    /// Region the generator jobs use (env AWS_DEFAULT_REGION)
     #[arg(long)]
-    clearing_bucket_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aws_region: Option<String>,
+    /// Generator threads per job
     #[arg(long)]
-    authorization_bucket_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    threads_per_job: Option<i32>,
+    /// Rows each generator thread writes per table
     #[arg(long)]
-    chargeback_bucket_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows_per_thread: Option<i32>,
+    /// Most jobs per Batch array
     #[arg(long)]
-    hash_pan_table_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_array_size: Option<i32>,
+    /// First day of an explicit backfill (YYYY-MM-DD); replaces the INITIAL_LOAD/nightly windows
     #[arg(long)]
-    partition_counter_table_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date: Option<chrono::NaiveDate>,
+    /// Last day of the backfill, inclusive (YYYY-MM-DD)
     #[arg(long)]
-    card_brand: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_date: Option<chrono::NaiveDate>,
+    /// Jobs per backfill day; derived from the target size when omitted
     #[arg(long)]
-    network_brand: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jobs_per_day: Option<i32>,
+    /// JSON day-of-week/month weights; jobs are spread in proportion instead of evenly
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_profile: Option<String>,
+    /// Bytes-per-row measurement from `GENERATOR_MODE=calibrate data-generator`; defaults to the bundled calibration.json
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calibration: Option<String>,
+    /// Price table for the cost estimate; defaults to the bundled prices.json
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prices: Option<String>,
+    /// Refuse to submit when the estimated cost exceeds this amount
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_cost: Option<f64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Poll the arrays of a submitted run until every job has finished
+    Watch {
+        #[command(flatten)]
+        run: RunSelector,
+        /// Seconds between status polls
+        #[arg(long, default_value_t = 60)]
+        poll_interval: u64,
+    },
+    /// Resubmit only the failed jobs of a run, reusing their partition orders
+    RetryFailed {
+        #[command(flatten)]
+        run: RunSelector,
+        /// Job indexes to retry instead of asking Batch which failed, e.g. 17,18,950
+        #[arg(long, value_delimiter = ',')]
+        job_index: Vec<i32>,
+        /// Queue for the resubmissions; defaults to each job's original queue
+        #[arg(long)]
+        queue: Option<String>,
+        /// Print the resubmissions without submitting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Stop every job of a run: its arrays, retries and steps
+    Cancel {
+        #[command(flatten)]
+        run: RunSelector,
+        /// Also stop jobs that are already running; cancelling only stops jobs that have not started
+        #[arg(long)]
+        terminate: bool,
+        /// Status reason recorded on each stopped job
+        #[arg(long, default_value = "Cancelled with job-submitter cancel")]
+        reason: String,
+        /// Once every job has stopped, purge the run as `job-submitter purge` does
+        #[arg(long)]
+        purge: bool,
+        /// Seconds between status polls while waiting for jobs to stop
+        #[arg(long, default_value_t = 30)]
+        poll_interval: u64,
+    },
+    /// Delete the files a finished or cancelled run wrote and reset its partition counter entries
+    Purge {
+        #[command(flatten)]
+        run: RunSelector,
+        /// Print what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// A run is read from its local manifest, or by ID from the ledger
+#[derive(clap::Args, Debug)]
+struct RunSelector {
+    /// Run manifest written by the submission
+    #[arg(long, default_value = "run-manifest.json")]
+    manifest: String,
+    /// Look the run up in the ledger instead of a local manifest
+    #[arg(long, requires = "ledger", conflicts_with = "manifest")]
+    run_id: Option<String>,
+    /// Run ledger, a directory or s3://bucket/prefix
+    #[arg(long)]
+    ledger: Option<String>,
+}
+
+impl RunSelector {
+    /// The run record, and the local manifest to keep in sync with the ledger
+    async fn load(&self) -> Result<(RunManifest, Option<&str>)> {
+        match (&self.run_id, &self.ledger) {
+            (Some(run_id), Some(location)) => Ok((ledger::Ledger::open(location).await?.load(run_id).await?, None)),
+            _ => Ok((RunManifest::load(&self.manifest)?, Some(self.manifest.as_str()))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
     let start_time = Instant::now();
+
+    let args = match cli.command {
+        Some(Command::Watch { run, poll_interval }) => {
+            let (mut manifest, manifest_path) = run.load().await?;
+            manifest.require_batch()?;
+            let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            return watch::watch_run(&BatchClient::new(&config), &mut manifest, manifest_path, Duration::from_secs(poll_interval)).await;
+        }
+        Some(Command::RetryFailed { run, job_index, queue, dry_run }) => {
+            let (mut manifest, manifest_path) = run.load().await?;
+            manifest.require_batch()?;
+            let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            if let Some(attempt) = retry::retry_failed(&BatchClient::new(&config), &manifest, &job_index, queue.as_deref(), dry_run).await? {
+                manifest.updated_at = attempt.submitted_at.clone();
+                manifest.retries.push(attempt);
+                manifest.status = RunStatus::Submitted;
+                ledger::record(&manifest, manifest_path).await?;
+                println!("📝 Retry {} recorded for run {} (follow it with `job-submitter watch`)", manifest.retries.len(), manifest.run_id);
+                let unsubmitted = manifest.latest_unsubmitted();
+                if !unsubmitted.is_empty() {
+                    bail!("{} jobs could not be resubmitted: {:?}; run retry-failed again once the queue accepts them", unsubmitted.len(), unsubmitted);
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Cancel { run, terminate, reason, purge, poll_interval }) => {
+            let (mut manifest, manifest_path) = run.load().await?;
+            manifest.require_batch()?;
+            let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            let client = BatchClient::new(&config);
+            let cancelled = cancel::cancel_run(&client, &manifest, terminate, &reason).await;
+            manifest.status = RunStatus::Cancelled;
+            manifest.updated_at = chrono::Utc::now().to_rfc3339();
+            ledger::record(&manifest, manifest_path).await?;
+            cancelled?;
+            if purge {
+                cancel::wait_until_finished(&client, &manifest, Duration::from_secs(poll_interval)).await?;
+                purge::purge_run(&config, &manifest, false).await?;
+                manifest.purged_at = Some(chrono::Utc::now().to_rfc3339());
+                manifest.updated_at = manifest.purged_at.clone().unwrap_or_default();
+                ledger::record(&manifest, manifest_path).await?;
+            }
+            return Ok(());
+        }
+        Some(Command::Purge { run, dry_run }) => {
+            let (mut manifest, manifest_path) = run.load().await?;
+            let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            // Jobs still running would write files after the purge
+            if manifest.executor == Executor::Batch {
+                let unfinished = cancel::unfinished_jobs(&BatchClient::new(&config), &manifest).await?;
+                if !unfinished.is_empty() {
+                    bail!("{} jobs of run {} have not finished; stop them with `job-submitter cancel --terminate` (or `cancel --purge`) first", unfinished.len(), manifest.run_id);
+                }
+            }
+            purge::purge_run(&config, &manifest, dry_run).await?;
+            if !dry_run {
+                manifest.purged_at = Some(chrono::Utc::now().to_rfc3339());
+                manifest.updated_at = manifest.purged_at.clone().unwrap_or_default();
+                ledger::record(&manifest, manifest_path).await?;
+                println!("📝 Purge recorded for run {}", manifest.run_id);
+            }
+            return Ok(());
+        }
+        None => cli.submit,
+    };
     
-    // Get target TB from environment variable
-    let target_tb: i32 = std::env::var("TARGET_TB")
-        .unwrap_or_else(|_| "500".to_string())
-        .parse()
-        .unwrap_or(500);
-    
-    let initial_load: bool = std::env::var("INITIAL_LOAD")
-        .unwrap_or_else(|_| "true".to_string())
-        .to_lowercase() == "true";
+    let spec = RunSpec::load(args.spec.as_deref(), &args.set, serde_json::to_value(&args.settings)?)?;
+    let (target_tb, initial_load, chargeback_percentage, threads_per_job, rows_per_thread) = (spec.target_tb, spec.initial_load, spec.chargeback_percentage, spec.threads_per_job, spec.rows_per_thread);
     
+    let calibration = Calibration::load(spec.calibration.as_deref())?;
+    let prices = cost::PriceTable::load(spec.prices.as_deref())?;
+    let bytes_per_job = calibration.bytes_for(&spec.tables.enabled(&TableCounts::for_job(threads_per_job, rows_per_thread, chargeback_percentage)));
+
     // Calculate job parameters dynamically
-    let (total_jobs, array_size) = calculate_job_parameters(target_tb);
+    let (mut total_jobs, array_size) = calculate_job_parameters(target_tb, bytes_per_job, spec.max_array_size);
+
+    // A backfill gives every day of the range the same number of jobs
+    let backfill = match (spec.start_date, spec.end_date) {
+        (Some(start_date), Some(end_date)) => {
+            let days = (end_date - start_date).num_days() as i32 + 1;
+            let jobs_per_day = spec.jobs_per_day.unwrap_or((total_jobs / days).max(1));
+            total_jobs = days * jobs_per_day;
+            Some((start_date, end_date, jobs_per_day))
+        }
+        _ => None,
+    };
     
-    let queues = vec![args.spot_queue_name.clone(), args.ondemand_queue_name.clone()];
+    // Local runs have no queues; their arrays only group jobs by brand
+    let (queues, queue_distribution) = if args.local {
+        spec.validate_local()?;
+        (vec![queues::QueueSpec { name: "local".to_string(), weight: 1.0, max_jobs: None }], queues::QueueDistribution::Weighted)
+    } else {
+        spec.validate_batch()?;
+        (spec.queues(), spec.queue_distribution)
+    };
 
     let today = chrono::Utc::now().naive_utc().date();
     let today_str = today.format("%Y-%m-%d").to_string();
@@ -82,163 +399,314 @@ async fn main() -> Result<()> {
     let six_days_ago = today - chrono::Duration::days(6);
     let six_days_ago_str = six_days_ago.format("%Y-%m-%d").to_string();
     
-    let cpu_per_job = 4; // 4 vCPUs per job
-    let memory_per_job_gb = 28; // 28GB RAM per job
-    let threads_per_job = 3; // 3 threads per job
+    let (cpu_per_job, memory_per_job_gb) = (spec.vcpus_per_job, spec.memory_per_job_gb);
+    if threads_per_job > cpu_per_job {
+        eprintln!("⚠️ {} threads per job share {} vCPUs, so they take turns", threads_per_job, cpu_per_job);
+    }
+
+    // The window is fixed here and passed to every job, so jobs never read the clock themselves
+    let (start_date, end_date) = match backfill {
+        Some((start_date, end_date, _)) => (start_date, end_date),
+        None if initial_load => (chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(), seven_days_ago),
+        None => (six_days_ago, today),
+    };
+    let date_profile = spec.date_profile.as_deref().map(DateProfile::load).transpose()?;
+    let brands = spec.brands();
+    let segments = brand_mix::split_brands(&brands, start_date, end_date, total_jobs, backfill.map(|(_, _, jobs_per_day)| jobs_per_day), date_profile.as_ref())?;
+    let plan = build_plan(
+        &PlanSettings {
+            target_tb,
+            initial_load,
+            array_size,
+            threads_per_job,
+            rows_per_thread,
+            chargeback_percentage,
+            vcpus_per_job: cpu_per_job,
+            memory_per_job_gb,
+            calibration: calibration.clone(),
+            prices: prices.clone(),
+            segments: segments.clone(),
+            tables: spec.tables,
+            queues: queues.clone(),
+            queue_distribution,
+        },
+    )?;
+
+    if args.dry_run && args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
     
+    let run_id = match &args.run_id {
+        Some(run_id) => {
+            manifest::validate_run_id(run_id)?;
+            run_id.clone()
+        }
+        None => manifest::new_run_id(),
+    };
+
     println!("--- Dynamic 6-Table Combined Upload Configuration ---");
+    if !args.dry_run {
+        println!("Run ID: {}", run_id);
+    }
     println!("Target data size: {}TB", target_tb);
-    println!("Card brand: {}", args.card_brand);
-    println!("Network brand: {}", args.network_brand);
-    println!("Load type: {}", if initial_load { 
+    println!("Estimated data size: {} ({} per job, {} stored across combined + specialized buckets)", format_bytes(plan.totals.estimated_bytes), format_bytes(bytes_per_job as u64), format_bytes(plan.totals.stored_bytes));
+    println!("Calibration: {} sample rows measured {} ({:.1} bytes/row authorization, {:.1} clearing, {:.1} authorization_hash)", calibration.sample_rows, calibration.created_at, calibration.bytes_per_row.authorization, calibration.bytes_per_row.clearing, calibration.bytes_per_row.authorization_hash);
+    if let [segment] = segments.as_slice() {
+        println!("Card brand: {}", segment.brand.card_brand);
+        println!("Network brand: {}", segment.brand.network_brand());
+    } else {
+        let total_weight: f64 = brands.iter().map(|brand| brand.weight).sum();
+        println!("Brand mix (card/network):");
+        for segment in &segments {
+            println!("  {}: {:.1}% weight, {} jobs ({}-{})",
+                     segment.brand.label(),
+                     segment.brand.weight / total_weight * 100.0,
+                     segment.jobs,
+                     segment.first_job_index,
+                     segment.first_job_index + segment.jobs - 1);
+        }
+    }
+    println!("Load type: {}", if let Some((start_date, end_date, jobs_per_day)) = backfill {
+        format!("Backfill ({} to {} inclusive - {} jobs per day)", start_date, end_date, jobs_per_day)
+    } else if initial_load { 
         format!("Initial Load (2020-01-01 to {} inclusive - UTC time)", seven_days_ago_str) 
     } else { 
         format!("Nightly Load ({} to {} inclusive - covers last 7 days - UTC time)", six_days_ago_str, today_str) 
     });
-    println!("Total jobs: {} (each generates 750k rows total: 3 threads × 250k rows × 6 tables)", total_jobs);
+    println!("Total jobs: {} (each generates {} rows per table: {} threads × {} rows)", plan.totals.jobs, threads_per_job * rows_per_thread, threads_per_job, rows_per_thread);
     println!("Array size: {}", array_size);
     println!("Container specs: {} vCPUs + {}GB RAM per job (optimized for combined uploads)", cpu_per_job, memory_per_job_gb);
     println!("Multi-threaded generation: {} threads per job → combined into 1 upload per table", threads_per_job);
     println!("Dual bucket uploads: Combined bucket + 3 specialized buckets (clearing, authorization, chargeback)");
-    println!("Using 2 queues for maximum scaling: {} jobs each", total_jobs / 2);
+    let local_parallelism = args.local_parallelism.unwrap_or_else(|| {
+        std::thread::available_parallelism().map(|cpus| cpus.get() / cpu_per_job as usize).unwrap_or(1).max(1)
+    });
+    if args.local {
+        println!("Local execution: {} jobs at a time with {}, output in {}", local_parallelism, args.generator_bin, args.local_output);
+    } else {
+        println!("Using {} queues ({} distribution):", queues.len(), queue_distribution.name());
+        for total in &plan.totals.jobs_per_queue {
+            println!("  {}: {} jobs", total.queue, total.jobs);
+        }
+        if let Some(fallback_queue) = &spec.fallback_queue {
+            println!("Fallback queue for failed submissions: {}", fallback_queue);
+        }
+        plan.totals.cost.print();
+        println!("Prices: {} list prices for {} as of {}, generator jobs at on-demand Fargate rates", prices.currency, prices.region, prices.updated);
+    }
+    if let Some(graph) = spec.steps.describe(plan.arrays.len()) {
+        println!("Dependency graph: {}", graph);
+    }
     println!("------------------------------\n");
 
-    let mut requests = Vec::new();
-    
-    // Calculate how many array jobs we need to submit all jobs
-    let total_arrays_needed = (total_jobs + array_size - 1) / array_size; // Ceiling division
-    let jobs_per_queue = total_jobs / 2;
-    let arrays_per_queue = (total_arrays_needed + 1) / 2; // Split arrays between queues
-    
-    println!("Using 2 queues with {} arrays each (max {} jobs per array)", arrays_per_queue, array_size);
-    
-    // Create multiple array jobs for each queue
-    let mut job_offset = 0;
-    for queue_idx in 0..2 {
-        let queue_name = &queues[queue_idx];
-        let remaining_jobs = total_jobs - job_offset;
-        let queue_jobs = std::cmp::min(jobs_per_queue, remaining_jobs);
-        
-        // Submit multiple arrays for this queue
-        let mut queue_job_offset = job_offset;
-        while queue_job_offset < job_offset + queue_jobs {
-            let remaining_in_queue = (job_offset + queue_jobs) - queue_job_offset;
-            let current_array_size = std::cmp::min(array_size, remaining_in_queue);
-            
-            requests.push((current_array_size, queue_name.clone(), queue_job_offset));
-            queue_job_offset += current_array_size;
+    let over_budget = spec.max_cost.filter(|max_cost| !args.local && plan.totals.cost.total_cost > *max_cost);
+    if args.dry_run {
+        print_plan(&plan);
+        if let Some(max_cost) = over_budget {
+            eprintln!("⚠️ Estimated cost {:.2} {} exceeds max_cost {:.2}; this run would be refused", plan.totals.cost.total_cost, prices.currency, max_cost);
+        }
+        return Ok(());
+    }
+    if let Some(max_cost) = over_budget {
+        bail!("Estimated cost {:.2} {} exceeds max_cost {:.2}; nothing was submitted (shrink the run or raise --max-cost)", plan.totals.cost.total_cost, prices.currency, max_cost);
+    }
+
+    // Shared by every array; brand, date allocation and JOB_INDEX_OFFSET are added per array, and
+    // the full set is kept in the run manifest
+    let mut base_environment: BTreeMap<String, String> = BTreeMap::from([
+        ("PAYMENT_DATA_BUCKET_NAME".to_string(), spec.payment_data_bucket_name.clone()),
+        ("CLEARING_BUCKET_NAME".to_string(), spec.clearing_bucket_name.clone()),
+        ("AUTHORIZATION_BUCKET_NAME".to_string(), spec.authorization_bucket_name.clone()),
+        ("CHARGEBACK_BUCKET_NAME".to_string(), spec.chargeback_bucket_name.clone()),
+        ("HASH_PAN_TABLE_NAME".to_string(), spec.hash_pan_table_name.clone()),
+        ("PARTITION_COUNTER_TABLE_NAME".to_string(), spec.partition_counter_table_name.clone()),
+        ("NUM_THREADS".to_string(), threads_per_job.to_string()),
+        ("NUM_OF_ROWS".to_string(), rows_per_thread.to_string()),
+        ("CHARGEBACK_PERCENTAGE".to_string(), chargeback_percentage.to_string()),
+        ("INITIAL_LOAD".to_string(), initial_load.to_string()),
+        ("AWS_DEFAULT_REGION".to_string(), spec.aws_region.clone()),
+        ("TABLE_OPTIONS".to_string(), serde_json::to_string(&spec.tables)?),
+        ("RUN_ID".to_string(), run_id.clone()),
+        ("LOG_FORMAT".to_string(), spec.log_format.name().to_string()),
+    ]);
+    if spec.metrics_emf {
+        base_environment.insert("METRICS_FORMAT".to_string(), "emf".to_string());
+    }
+    let segment_environments = segments
+        .iter()
+        .map(|segment| segment.job_environment())
+        .collect::<Result<Vec<_>>>()?;
+    let array_environment = |array: &ArrayJobPlan| {
+        let mut environment = base_environment.clone();
+        for (name, value) in &segment_environments[array.segment] {
+            environment.insert(name.to_string(), value.clone());
+        }
+        environment.insert("JOB_INDEX_OFFSET".to_string(), array.job_index_offset.to_string());
+        environment
+    };
+
+    let submitted_at = chrono::Utc::now().to_rfc3339();
+    let run_config = RunConfig {
+        spec: spec.clone(),
+        window_start: plan.start_date.clone(),
+        window_end: plan.end_date.clone(),
+        date_allocation: plan.date_allocation.clone(),
+        threads_per_job,
+        estimated_bytes: plan.totals.estimated_bytes,
+    };
+    let new_manifest = |executor, status, job_definition, arrays: Vec<SubmittedArray>, default_ledger: String| RunManifest {
+        run_id: run_id.clone(),
+        status,
+        submitted_at: submitted_at.clone(),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+        ledger: Some(args.ledger.clone().unwrap_or(default_ledger)),
+        executor,
+        job_definition,
+        total_jobs: arrays.iter().map(|array| array.size).sum(),
+        config: run_config.clone(),
+        arrays,
+        steps: Vec::new(),
+        retries: Vec::new(),
+        local_failures: Vec::new(),
+        purged_at: None,
+    };
+
+    if args.local {
+        let output_dir = std::path::Path::new(&args.local_output);
+        let arrays: Vec<SubmittedArray> = plan
+            .arrays
+            .iter()
+            .enumerate()
+            .map(|(batch_num, array)| {
+                let mut environment = array_environment(array);
+                environment.insert("OUTPUT_DIR".to_string(), output_dir.join("data").display().to_string());
+                environment.insert("METRICS_DIR".to_string(), output_dir.join("metrics").display().to_string());
+                SubmittedArray {
+                    batch_num,
+                    job_id: format!("local-{}-batch-{}", run_id, batch_num + 1),
+                    job_name: format!("6table-multiple-buckets-{}-batch-{}", run_id, batch_num + 1),
+                    queue: array.queue.clone(),
+                    size: array.size,
+                    job_index_offset: array.job_index_offset,
+                    environment,
+                    retry_of: None,
+                }
+            })
+            .collect();
+        let executor = local::LocalExecutor {
+            generator_bin: args.generator_bin.clone().into(),
+            parallelism: local_parallelism,
+            log_dir: output_dir.join("logs"),
+        };
+        let step_environment = |step: &StepSpec| {
+            let mut environment = step.job_environment(&base_environment);
+            environment.insert("OUTPUT_DIR".to_string(), output_dir.join("data").display().to_string());
+            environment
+        };
+
+        // Same order as the Batch dependency graph: nothing runs after a failed step or job
+        let mut steps = Vec::new();
+        let mut failed_step = None;
+        if let Some(seed) = &spec.steps.seed {
+            let step = executor.run_step(&run_id, "seed", seed, &step_environment(seed)).await?;
+            if step.exit_code != Some(0) {
+                failed_step = Some("seed");
+            }
+            steps.push(step);
+        }
+        let failures = match failed_step {
+            None => executor.run(&arrays).await?,
+            Some(_) => Vec::new(),
+        };
+        if let (Some(validate), None, true) = (&spec.steps.validate, failed_step, failures.is_empty()) {
+            let step = executor.run_step(&run_id, "validate", validate, &step_environment(validate)).await?;
+            if step.exit_code != Some(0) {
+                failed_step = Some("validate");
+            }
+            steps.push(step);
+        }
+
+        let status = if failures.is_empty() && failed_step.is_none() { RunStatus::Succeeded } else { RunStatus::Failed };
+        let mut manifest = new_manifest(Executor::Local, status, args.generator_bin.clone(), arrays, output_dir.join("runs").display().to_string());
+        manifest.steps = steps;
+        manifest.local_failures = failures;
+        ledger::record(&manifest, Some(&args.manifest)).await?;
+        println!("📝 Run {} recorded in {} and {} ({:.1}s)", run_id, args.manifest, manifest.ledger.as_deref().unwrap_or_default(), start_time.elapsed().as_secs_f64());
+        if let Some(name) = failed_step {
+            bail!("Step {} of run {} failed (log: {})", name, run_id, output_dir.join("logs").join(format!("{}.log", name)).display());
         }
-        
-        job_offset += queue_jobs;
+        if !manifest.local_failures.is_empty() {
+            let job_indexes: Vec<String> = manifest.local_failures.iter().map(|failure| failure.job_index.to_string()).collect();
+            bail!("{} of {} local jobs failed: {}", job_indexes.len(), manifest.total_jobs, job_indexes.join(","));
+        }
+        println!("🎉 All {} local jobs succeeded; files are under {}, job metrics under {}", manifest.total_jobs, output_dir.join("data").display(), output_dir.join("metrics").display());
+        return Ok(());
     }
 
+    println!("Submitting {} arrays (max {} jobs per array)", plan.arrays.len(), array_size);
+
     let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
     let client = BatchClient::new(&config);
 
-    let requests_len = requests.len();
-    println!("🔧 Using job definition: {}", args.job_definition);
+    let requests_len = plan.arrays.len();
+    let job_definition = spec.job_definition.clone().unwrap_or_default();
+    println!("🔧 Using job definition: {}", job_definition);
     println!("🔧 Submitting {} batches to queues", requests_len);
-    
-    let mut submission_stream = stream::iter(requests.into_iter().enumerate())
-        .map(|(batch_num, (array_size, queue_name, start_index))| {
+
+    let batch_steps = steps::BatchSteps {
+        client: &client,
+        run_id: &run_id,
+        default_queue: &queues[0].name,
+        barrier_job_definition: &job_definition,
+        base_environment: &base_environment,
+    };
+    let mut submitted_steps = Vec::new();
+    if let Some(seed) = &spec.steps.seed {
+        submitted_steps = batch_steps.submit("seed", seed, Vec::new()).await?;
+    }
+    // Every array waits for the seed job
+    let seed_dependency: Vec<String> = submitted_steps.iter().map(|step| step.job_id.clone()).collect();
+
+    let mut submission_stream = stream::iter(plan.arrays.into_iter().enumerate())
+        .map(|(batch_num, array)| {
+            let environment = array_environment(&array);
+            let (array_size, queue_name, start_index) = (array.size, array.queue, array.job_index_offset);
             println!("🚀 Submitting batch {} to queue: {}", batch_num + 1, queue_name);
             let client = client.clone();
-            let job_definition = args.job_definition.clone();
-            let payment_data_bucket_name = args.payment_data_bucket_name.clone();
-            let clearing_bucket_name = args.clearing_bucket_name.clone();
-            let authorization_bucket_name = args.authorization_bucket_name.clone();
-            let chargeback_bucket_name = args.chargeback_bucket_name.clone();
-            let hash_pan_table_name = args.hash_pan_table_name.clone();
-            let partition_counter_table_name = args.partition_counter_table_name.clone();
-            let card_brand = args.card_brand.clone();
-            let network_brand = args.network_brand.clone();
-            
+            let run_id = run_id.clone();
+            let job_definition = job_definition.clone();
+            let fallback_queue = spec.fallback_queue.clone();
+            let depends_on = seed_dependency.clone();
+
             tokio::spawn(async move {
-                let job_name = format!("6table-multiple-buckets-batch-{}", batch_num + 1);
-                let start_index = start_index;
-                
-                let container_overrides = ContainerOverrides::builder()
-                    .environment(KeyValuePair::builder()
-                        .name("PAYMENT_DATA_BUCKET_NAME")
-                        .value(&payment_data_bucket_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("CLEARING_BUCKET_NAME")
-                        .value(&clearing_bucket_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("AUTHORIZATION_BUCKET_NAME")
-                        .value(&authorization_bucket_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("CHARGEBACK_BUCKET_NAME")
-                        .value(&chargeback_bucket_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("HASH_PAN_TABLE_NAME")
-                        .value(&hash_pan_table_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("PARTITION_COUNTER_TABLE_NAME")
-                        .value(&partition_counter_table_name)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("JOB_INDEX_OFFSET")
-                        .value(start_index.to_string())
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("NUM_OF_ROWS")
-                        .value(NUM_OF_ROWS.to_string())
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("CHARGEBACK_PERCENTAGE")
-                        .value(std::env::var("CHARGEBACK_PERCENTAGE").unwrap_or_else(|_| "0.1".to_string()))
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("INITIAL_LOAD")
-                        .value(initial_load.to_string())
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("AWS_DEFAULT_REGION")
-                        .value(std::env::var("AWS_DEFAULT_REGION").unwrap_or_else(|_| "us-east-1".to_string()))
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("CARD_BRAND")
-                        .value(card_brand)
-                        .build())
-                    .environment(KeyValuePair::builder()
-                        .name("NETWORK_BRAND")
-                        .value(network_brand)
-                        .build())
-                    .build();
-
-                let result = client
-                    .submit_job()
-                    .job_name(&job_name)
-                    .job_queue(&queue_name)
-                    .job_definition(&job_definition)
-                    .array_properties(
-                        aws_sdk_batch::types::ArrayProperties::builder()
-                            .size(array_size)
-                            .build()
-                    )
-                    .container_overrides(container_overrides)
-                    .send()
-                    .await;
+                let job_name = format!("6table-multiple-buckets-{}-batch-{}", run_id, batch_num + 1);
+                let target = submission::QueueTarget { queue: &queue_name, fallback_queue: fallback_queue.as_deref() };
+                let container = submission::JobContainer { job_definition: &job_definition, command: &[], environment: &environment, depends_on: &depends_on };
+                let result = submission::submit_with_fallback(&client, &run_id, &job_name, target, &container, array_size).await;
 
                 match result {
-                    Ok(response) => {
+                    Ok((job_id, queue_name)) => {
                         println!("✅ Batch {} submitted: {} (jobs {}-{}) to {}", 
                                 batch_num + 1, 
-                                response.job_id().unwrap_or("unknown"),
+                                job_id,
                                 start_index,
                                 start_index + array_size - 1,
                                 queue_name);
-                        Ok::<i32, anyhow::Error>(array_size)
+                        Ok::<SubmittedArray, anyhow::Error>(SubmittedArray {
+                            batch_num,
+                            job_id,
+                            job_name,
+                            queue: queue_name,
+                            size: array_size,
+                            job_index_offset: start_index,
+                            environment,
+                            retry_of: None,
+                        })
                     }
                     Err(e) => {
                         eprintln!("❌ Batch {} failed: {:?}", batch_num + 1, e);
-                        Err(e.into())
+                        Err(e)
                     }
                 }
             })
@@ -247,12 +715,14 @@ async fn main() -> Result<()> {
 
     let mut submitted_jobs = 0;
     let mut submitted_batches = 0;
+    let mut submitted_arrays = Vec::with_capacity(requests_len);
 
     while let Some(result) = submission_stream.next().await {
         match result {
-            Ok(Ok(array_size)) => {
+            Ok(Ok(array)) => {
                 submitted_batches += 1;
-                submitted_jobs += array_size;
+                submitted_jobs += array.size;
+                submitted_arrays.push(array);
                 if submitted_batches % 10 == 0 {
                     println!("Progress: {}/{} batches submitted ({} jobs)", 
                              submitted_batches, requests_len, submitted_jobs);
@@ -268,9 +738,29 @@ async fn main() -> Result<()> {
     }
 
     println!("\n🎉 All {} job batches submitted successfully!", submitted_batches);
-    println!("Total jobs submitted: {} (each with {} threads = {} total threads)", submitted_jobs, cpu_per_job - 1, submitted_jobs * (cpu_per_job - 1));
-    println!("Expected data generation: ~{}TB across dual bucket architecture", target_tb);
+    println!("Total jobs submitted: {} (each with {} threads = {} total threads)", submitted_jobs, threads_per_job, submitted_jobs * threads_per_job);
+    let submitted_bytes = (bytes_per_job * submitted_jobs as f64) as u64;
+    println!("Expected data generation: ~{} ({} across dual bucket architecture)", format_bytes(submitted_bytes), format_bytes(submitted_bytes * 2));
     println!("Submission completed in {:.1}s", start_time.elapsed().as_secs_f64());
+
+    submitted_arrays.sort_by_key(|array| array.batch_num);
+    if let Some(validate) = &spec.steps.validate {
+        // Validating a partial run would only report the missing arrays
+        if submitted_batches == requests_len {
+            let array_job_ids = submitted_arrays.iter().map(|array| array.job_id.clone()).collect();
+            submitted_steps.extend(batch_steps.submit("validate", validate, array_job_ids).await?);
+        } else {
+            eprintln!("⚠️ Validate step not submitted: {} of {} arrays failed to submit", requests_len - submitted_batches, requests_len);
+        }
+    }
+    let mut manifest = new_manifest(Executor::Batch, RunStatus::Submitted, job_definition.clone(), submitted_arrays, format!("s3://{}/runs", spec.payment_data_bucket_name));
+    manifest.steps = submitted_steps;
+    ledger::record(&manifest, Some(&args.manifest)).await?;
+    println!("📝 Run {} recorded in {} and {} (follow it with `job-submitter watch --manifest {}`)", run_id, args.manifest, manifest.ledger.as_deref().unwrap_or_default(), args.manifest);
+
+    if args.wait {
+        watch::watch_run(&client, &mut manifest, Some(&args.manifest), Duration::from_secs(args.poll_interval)).await?;
+    }
     
     Ok(())
 }
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::local::LocalJobResult;
+use crate::spec::RunSpec;
+
+/// An array job accepted by Batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedArray {
+    pub batch_num: usize,
+    pub job_id: String,
+    pub job_name: String,
+    pub queue: String,
+    pub size: i32,
+    /// Child i of the array runs job index job_index_offset + i
+    pub job_index_offset: i32,
+    /// Container environment the children ran with, reused by `retry-failed`
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+    /// Set on resubmissions: the array whose partition orders the children reuse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<RetrySource>,
+}
+
+/// A seed or validate step of the run, or a barrier joining more jobs than a step can depend on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedStep {
+    pub name: String,
+    pub job_id: String,
+    pub job_name: String,
+    pub queue: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// How the step exited in a local run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+/// The array a job index was first submitted in; the generator's partition order claims are
+/// keyed by its job ID and child index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrySource {
+    pub array_job_id: String,
+    pub job_index_offset: i32,
+    /// False when the original was a single job: Batch submits size-1 arrays without array
+    /// properties, so its job ID has no `:<index>` suffix
+    #[serde(default = "array_child_default")]
+    pub array_child: bool,
+}
+
+// Manifests written before `array_child` only recorded resubmissions of real arrays correctly
+fn array_child_default() -> bool {
+    true
+}
+
+/// Overall state of a run; `watch` sets the final status, `cancel` marks it cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RunStatus {
+    Submitted,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// What ran the jobs: AWS Batch, or `--local` subprocesses on the submitting machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Executor {
+    #[default]
+    Batch,
+    Local,
+}
+
+/// Settings a run was submitted with: the resolved run spec plus what was derived from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub spec: RunSpec,
+    pub window_start: String,
+    pub window_end: String,
+    pub date_allocation: String,
+    pub threads_per_job: i32,
+    pub estimated_bytes: u64,
+}
+
+/// Jobs resubmitted by one `retry-failed`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryAttempt {
+    pub submitted_at: String,
+    pub arrays: Vec<SubmittedArray>,
+    /// Job indexes whose resubmission Batch did not accept; they count as failed jobs of the attempt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsubmitted_job_indexes: Vec<i32>,
+}
+
+/// Ledger record of a run: what was handed to Batch, with what settings, and how it ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub run_id: String,
+    pub status: RunStatus,
+    pub submitted_at: String,
+    pub updated_at: String,
+    /// Ledger location the record is kept in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger: Option<String>,
+    #[serde(default)]
+    pub executor: Executor,
+    pub job_definition: String,
+    pub total_jobs: i32,
+    pub config: RunConfig,
+    pub arrays: Vec<SubmittedArray>,
+    /// Seed, barrier and validate jobs, in submission order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<SubmittedStep>,
+    #[serde(default)]
+    pub retries: Vec<RetryAttempt>,
+    /// Jobs of a local run that exited non-zero
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_failures: Vec<LocalJobResult>,
+    /// When `purge` deleted the run's files and partition counter entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purged_at: Option<String>,
+}
+
+impl RunManifest {
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("Failed to write run manifest {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read run manifest {}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("Invalid run manifest {}: {}", path, e))
+    }
+
+    /// `watch`, `retry-failed` and `cancel` act on the run's Batch jobs
+    pub fn require_batch(&self) -> Result<()> {
+        if self.executor == Executor::Local {
+            return Err(anyhow!("Run {} ran locally with --local; its job results are in the run record", self.run_id));
+        }
+        Ok(())
+    }
+
+    /// Arrays of every attempt, the first submission then each retry
+    pub fn all_arrays(&self) -> impl Iterator<Item = &SubmittedArray> {
+        self.arrays.iter().chain(self.retries.iter().flat_map(|retry| &retry.arrays))
+    }
+
+    /// Arrays of the latest attempt: each retry covers exactly what the attempt before it left failed
+    pub fn latest_arrays(&self) -> &[SubmittedArray] {
+        self.retries.last().map(|retry| retry.arrays.as_slice()).unwrap_or(&self.arrays)
+    }
+
+    /// Job indexes the latest retry failed to resubmit
+    pub fn latest_unsubmitted(&self) -> &[i32] {
+        self.retries.last().map(|retry| retry.unsubmitted_job_indexes.as_slice()).unwrap_or(&[])
+    }
+
+    /// The array a job index was first submitted in
+    pub fn original_array(&self, job_index: i32) -> Result<&SubmittedArray> {
+        self.arrays
+            .iter()
+            .find(|array| (array.job_index_offset..array.job_index_offset + array.size).contains(&job_index))
+            .ok_or_else(|| anyhow!("Job index {} is not part of this run", job_index))
+    }
+}
+
+/// A run ID that sorts by submission time and is valid in Batch job names and tags
+pub fn new_run_id() -> String {
+    let now = chrono::Utc::now();
+    let suffix = (now.timestamp_subsec_nanos() ^ std::process::id()) & 0xffff;
+    format!("{}-{:04x}", now.format("%Y%m%d-%H%M%S"), suffix)
+}
+
+/// Run IDs go into file names as run_<run_id>_job_..., so they may not contain '_' themselves:
+/// run a_2 would otherwise share its file prefix with run a
+pub fn validate_run_id(run_id: &str) -> Result<()> {
+    if run_id.is_empty() || run_id.len() > 64 || !run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("Invalid run ID {:?}: use up to 64 letters, digits or '-'", run_id));
+    }
+    Ok(())
+}
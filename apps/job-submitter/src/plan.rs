@@ -0,0 +1,261 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::brand_mix::BrandSegment;
+use crate::calibration::Calibration;
+use crate::cost::{CostEstimate, JobUsage, PriceTable};
+use crate::queues::{QueueDistribution, QueueSpec};
+use crate::spec::TableSpecs;
+
+/// A row or byte count for each of the six tables
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TableCounts {
+    pub authorization: u64,
+    pub authorization_hash: u64,
+    pub clearing: u64,
+    pub clearing_hash: u64,
+    pub chargeback: u64,
+    pub chargeback_hash: u64,
+}
+
+impl TableCounts {
+    /// Rows each table receives from a single job
+    pub fn for_job(threads_per_job: i32, rows_per_thread: i32, chargeback_percentage: f64) -> Self {
+        let transactions = (threads_per_job as u64) * (rows_per_thread as u64);
+        // Same truncation as the generator: chargebacks are picked per thread
+        let chargebacks = (threads_per_job as u64) * ((rows_per_thread as f64 * chargeback_percentage / 100.0) as u64);
+        Self {
+            authorization: transactions,
+            authorization_hash: transactions,
+            clearing: transactions,
+            clearing_hash: transactions,
+            chargeback: chargebacks,
+            chargeback_hash: chargebacks,
+        }
+    }
+
+    fn scaled(&self, jobs: i32) -> Self {
+        let jobs = jobs as u64;
+        Self {
+            authorization: self.authorization * jobs,
+            authorization_hash: self.authorization_hash * jobs,
+            clearing: self.clearing * jobs,
+            clearing_hash: self.clearing_hash * jobs,
+            chargeback: self.chargeback * jobs,
+            chargeback_hash: self.chargeback_hash * jobs,
+        }
+    }
+}
+
+/// One Batch array job as it would be submitted
+#[derive(Debug, Clone, Serialize)]
+pub struct ArrayJobPlan {
+    pub batch_num: usize,
+    pub queue: String,
+    pub card_brand: String,
+    pub network_brand: String,
+    /// Index into the plan's brand segments
+    #[serde(skip)]
+    pub segment: usize,
+    pub size: i32,
+    /// JOB_INDEX_OFFSET passed to the array; its children cover offset..=last_job_index
+    pub job_index_offset: i32,
+    pub last_job_index: i32,
+    pub rows_per_table: TableCounts,
+    pub estimated_bytes: u64,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueTotal {
+    pub queue: String,
+    pub jobs: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanTotals {
+    pub arrays: usize,
+    pub jobs: i32,
+    pub jobs_per_queue: Vec<QueueTotal>,
+    pub rows_per_table: TableCounts,
+    pub estimated_bytes: u64,
+    pub estimated_bytes_per_table: TableCounts,
+    /// Tables are written to the combined bucket and, unless turned off per table, to their specialized bucket
+    pub stored_bytes: u64,
+    pub cost: CostEstimate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobPlan {
+    pub target_tb: i32,
+    pub initial_load: bool,
+    pub threads_per_job: i32,
+    pub rows_per_thread: i32,
+    pub start_date: String,
+    pub end_date: String,
+    pub date_allocation: String,
+    /// Fewest and most jobs any day of the window receives
+    pub min_jobs_per_day: u64,
+    pub max_jobs_per_day: u64,
+    pub arrays: Vec<ArrayJobPlan>,
+    pub totals: PlanTotals,
+}
+
+pub struct PlanSettings {
+    pub target_tb: i32,
+    pub initial_load: bool,
+    pub array_size: i32,
+    pub threads_per_job: i32,
+    pub rows_per_thread: i32,
+    pub chargeback_percentage: f64,
+    pub vcpus_per_job: i32,
+    pub memory_per_job_gb: i32,
+    pub calibration: Calibration,
+    pub prices: PriceTable,
+    /// One per brand, in job index order; every segment allocates over the same window
+    pub segments: Vec<BrandSegment>,
+    pub tables: TableSpecs,
+    pub queues: Vec<QueueSpec>,
+    pub queue_distribution: QueueDistribution,
+}
+
+/// Share each brand's jobs between the queues and cut them into arrays of at most `array_size` jobs
+pub fn build_plan(settings: &PlanSettings) -> Result<JobPlan> {
+    let job_rows = settings.tables.enabled(&TableCounts::for_job(settings.threads_per_job, settings.rows_per_thread, settings.chargeback_percentage));
+    let date_allocator = &settings.segments[0].date_allocator;
+    let (window_start, window_end) = date_allocator.window();
+
+    // Partition date of every job, used for per-array coverage and the per-day spread
+    let job_dates = settings
+        .segments
+        .iter()
+        .flat_map(|segment| (segment.first_job_index..segment.first_job_index + segment.jobs).map(|job_index| segment.date_for_job(job_index)))
+        .collect::<Result<Vec<_>>>()?;
+    let mut jobs_per_day: BTreeMap<chrono::NaiveDate, u64> = window_start.iter_days().take_while(|date| *date <= window_end).map(|date| (date, 0)).collect();
+    for date in &job_dates {
+        *jobs_per_day.entry(*date).or_default() += 1;
+    }
+
+    let mut arrays = Vec::new();
+    let mut remaining_capacity: Vec<Option<i32>> = settings.queues.iter().map(|queue| queue.max_jobs).collect();
+    for (segment_idx, segment) in settings.segments.iter().enumerate() {
+        // Every job of the segment goes to exactly one queue, so no job index (and no backfill day) is dropped
+        let queue_shares = settings.queue_distribution.split(segment.jobs, &settings.queues, &mut remaining_capacity);
+        let mut job_offset = segment.first_job_index;
+        for (queue, queue_jobs) in settings.queues.iter().zip(queue_shares) {
+            let mut queue_job_offset = job_offset;
+            while queue_job_offset < job_offset + queue_jobs {
+                let remaining_in_queue = (job_offset + queue_jobs) - queue_job_offset;
+                let size = std::cmp::min(settings.array_size, remaining_in_queue);
+                let array_dates = &job_dates[queue_job_offset as usize..(queue_job_offset + size) as usize];
+
+                arrays.push(ArrayJobPlan {
+                    batch_num: arrays.len() + 1,
+                    queue: queue.name.clone(),
+                    card_brand: segment.brand.card_brand.clone(),
+                    network_brand: segment.brand.network_brand().to_string(),
+                    segment: segment_idx,
+                    size,
+                    job_index_offset: queue_job_offset,
+                    last_job_index: queue_job_offset + size - 1,
+                    rows_per_table: job_rows.scaled(size),
+                    estimated_bytes: settings.calibration.bytes_for(&job_rows.scaled(size)) as u64,
+                    start_date: array_dates.iter().min().map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                    end_date: array_dates.iter().max().map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                });
+                queue_job_offset += size;
+            }
+
+            job_offset += queue_jobs;
+        }
+    }
+
+    let jobs = arrays.iter().map(|array| array.size).sum();
+    let jobs_per_queue = settings
+        .queues
+        .iter()
+        .map(|queue| QueueTotal {
+            queue: queue.name.clone(),
+            jobs: arrays.iter().filter(|array| array.queue == queue.name).map(|array| array.size).sum(),
+        })
+        .collect();
+    let rows_per_table = job_rows.scaled(jobs);
+    let estimated_bytes = settings.calibration.bytes_for(&rows_per_table) as u64;
+    let stored_bytes = estimated_bytes + settings.calibration.bytes_for(&settings.tables.specialized(&rows_per_table)) as u64;
+    let thread_rows = settings.tables.enabled(&TableCounts::for_job(1, settings.rows_per_thread, settings.chargeback_percentage));
+    // Threads beyond the job's vCPUs wait for one to free up
+    let thread_waves = (settings.threads_per_job as u32).div_ceil(settings.vcpus_per_job as u32);
+    let job_usage = JobUsage {
+        vcpus: settings.vcpus_per_job,
+        memory_gb: settings.memory_per_job_gb,
+        threads: settings.threads_per_job,
+        seconds: settings.calibration.seconds_for(&thread_rows) * thread_waves as f64,
+        uploads_per_thread: settings.tables.uploads_per_thread(),
+    };
+    let totals = PlanTotals {
+        arrays: arrays.len(),
+        jobs,
+        jobs_per_queue,
+        rows_per_table,
+        estimated_bytes,
+        estimated_bytes_per_table: settings.calibration.bytes_per_table(&rows_per_table),
+        stored_bytes,
+        cost: CostEstimate::new(&settings.prices, jobs, &job_usage, stored_bytes),
+    };
+
+    Ok(JobPlan {
+        target_tb: settings.target_tb,
+        initial_load: settings.initial_load,
+        threads_per_job: settings.threads_per_job,
+        rows_per_thread: settings.rows_per_thread,
+        start_date: window_start.format("%Y-%m-%d").to_string(),
+        end_date: window_end.format("%Y-%m-%d").to_string(),
+        date_allocation: date_allocator.name().to_string(),
+        min_jobs_per_day: jobs_per_day.values().copied().min().unwrap_or(0),
+        max_jobs_per_day: jobs_per_day.values().copied().max().unwrap_or(0),
+        arrays,
+        totals,
+    })
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.2}{}", value, UNITS[unit])
+}
+
+pub fn print_plan(plan: &JobPlan) {
+    println!("--- Dry-run plan (nothing will be submitted) ---");
+    println!("{:>5}  {:<40} {:<21} {:>5}  {:>21}  {:>14}  {:>11}  {:>10}  Dates", "Batch", "Queue", "Brand", "Size", "JOB_INDEX_OFFSET", "Rows/table", "Chargebacks", "Est. size");
+    for array in &plan.arrays {
+        println!(
+            "{:>5}  {:<40} {:<21} {:>5}  {:>21}  {:>14}  {:>11}  {:>10}  {} to {}",
+            array.batch_num,
+            array.queue,
+            format!("{}/{}", array.card_brand, array.network_brand),
+            array.size,
+            format!("{}-{}", array.job_index_offset, array.last_job_index),
+            array.rows_per_table.authorization,
+            array.rows_per_table.chargeback,
+            format_bytes(array.estimated_bytes),
+            array.start_date,
+            array.end_date,
+        );
+    }
+    println!("------------------------------");
+    println!("Arrays: {}", plan.totals.arrays);
+    println!("Jobs: {} ({} threads × {} rows each)", plan.totals.jobs, plan.threads_per_job, plan.rows_per_thread);
+    let queues: Vec<String> = plan.totals.jobs_per_queue.iter().map(|total| format!("{} {}", total.queue, total.jobs)).collect();
+    println!("Jobs per queue: {}", queues.join(", "));
+    let rows = &plan.totals.rows_per_table;
+    println!("Rows per table: authorization {}, authorization_hash {}, clearing {}, clearing_hash {}, chargeback {}, chargeback_hash {}", rows.authorization, rows.authorization_hash, rows.clearing, rows.clearing_hash, rows.chargeback, rows.chargeback_hash);
+    println!("Estimated size: {} ({} stored across combined + specialized buckets)", format_bytes(plan.totals.estimated_bytes), format_bytes(plan.totals.stored_bytes));
+    println!("Date coverage: {} to {} ({} allocation, {}-{} jobs per day)", plan.start_date, plan.end_date, plan.date_allocation, plan.min_jobs_per_day, plan.max_jobs_per_day);
+}
@@ -0,0 +1,321 @@
+use anyhow::{anyhow, bail, Result};
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use aws_sdk_s3::Client as S3Client;
+use chrono::NaiveDate;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::manifest::{Executor, RunManifest};
+use crate::plan::format_bytes;
+use crate::spec::RunSpec;
+
+const CONCURRENT_REQUESTS: usize = 50;
+// DeleteObjects accepts at most 1000 keys per call
+const DELETE_CHUNK_SIZE: usize = 1000;
+// Claims removed per UpdateItem, well inside the 4 KB expression limit
+const CLAIMS_PER_UPDATE: usize = 50;
+
+/// Where a run's files are: S3, or the local directory a `--local` run wrote to
+enum Objects {
+    S3(S3Client),
+    Local(PathBuf),
+}
+
+impl Objects {
+    /// Keys and sizes of the objects under a prefix
+    async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<(String, u64)>> {
+        match self {
+            Objects::S3(client) => {
+                let mut objects = Vec::new();
+                let mut continuation_token = None;
+                loop {
+                    let response = client
+                        .list_objects_v2()
+                        .bucket(bucket)
+                        .prefix(prefix)
+                        .set_continuation_token(continuation_token)
+                        .send()
+                        .await
+                        .map_err(|e| anyhow!("Failed to list s3://{}/{}: {:?}", bucket, prefix, e))?;
+                    for object in response.contents() {
+                        if let Some(key) = object.key() {
+                            objects.push((key.to_string(), object.size().unwrap_or(0).max(0) as u64));
+                        }
+                    }
+                    continuation_token = response.next_continuation_token().map(str::to_string);
+                    if continuation_token.is_none() {
+                        return Ok(objects);
+                    }
+                }
+            }
+            Objects::Local(dir) => {
+                let (key_dir, name_prefix) = prefix.rsplit_once('/').unwrap_or(("", prefix));
+                let entries = match std::fs::read_dir(dir.join(bucket).join(key_dir)) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => bail!("Failed to list {}/{}/{}: {}", dir.display(), bucket, key_dir, e),
+                };
+                let mut objects = Vec::new();
+                for entry in entries {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.starts_with(name_prefix) {
+                        objects.push((format!("{}/{}", key_dir, name), entry.metadata()?.len()));
+                    }
+                }
+                Ok(objects)
+            }
+        }
+    }
+
+    async fn delete(&self, bucket: &str, keys: &[String]) -> Result<()> {
+        match self {
+            Objects::S3(client) => {
+                for chunk in keys.chunks(DELETE_CHUNK_SIZE) {
+                    let objects = chunk
+                        .iter()
+                        .map(|key| ObjectIdentifier::builder().key(key).build())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let response = client
+                        .delete_objects()
+                        .bucket(bucket)
+                        .delete(Delete::builder().set_objects(Some(objects)).quiet(true).build()?)
+                        .send()
+                        .await
+                        .map_err(|e| anyhow!("Failed to delete objects from s3://{}: {:?}", bucket, e))?;
+                    if let Some(error) = response.errors().first() {
+                        bail!("Failed to delete {} objects from s3://{}, e.g. {}: {}", response.errors().len(), bucket, error.key().unwrap_or_default(), error.message().unwrap_or("no message"));
+                    }
+                }
+            }
+            Objects::Local(dir) => {
+                for key in keys {
+                    let path = dir.join(bucket).join(key);
+                    std::fs::remove_file(&path).map_err(|e| anyhow!("Failed to delete {}: {}", path.display(), e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Buckets each table is written to: the combined bucket, plus its specialized bucket
+fn table_buckets(spec: &RunSpec) -> Vec<(&'static str, String)> {
+    let tables = [
+        ("authorization", spec.tables.authorization, &spec.authorization_bucket_name),
+        ("authorization_hash", spec.tables.authorization_hash, &spec.authorization_bucket_name),
+        ("clearing", spec.tables.clearing, &spec.clearing_bucket_name),
+        ("clearing_hash", spec.tables.clearing_hash, &spec.clearing_bucket_name),
+        ("chargeback", spec.tables.chargeback, &spec.chargeback_bucket_name),
+        ("chargeback_hash", spec.tables.chargeback_hash, &spec.chargeback_bucket_name),
+    ];
+    let mut locations = Vec::new();
+    for (table, options, specialized_bucket) in tables.into_iter().filter(|(_, options, _)| options.enabled) {
+        locations.push((table, spec.payment_data_bucket_name.clone()));
+        if options.specialized_bucket {
+            locations.push((table, specialized_bucket.clone()));
+        }
+    }
+    locations
+}
+
+/// Whether `key` names a file of `run_id`: exactly run_<run_id>_job_<n>_thread_<t>.parquet. The
+/// listing prefix alone also matches runs whose ID starts with `<run_id>_`, as older IDs may.
+fn is_run_file(key: &str, run_id: &str) -> bool {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    let digits = |value: &str| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+    name.strip_prefix("run_")
+        .and_then(|rest| rest.strip_prefix(run_id))
+        .and_then(|rest| rest.strip_prefix("_job_"))
+        .and_then(|rest| rest.strip_suffix(".parquet"))
+        .and_then(|rest| rest.split_once("_thread_"))
+        .is_some_and(|(job, thread)| digits(job) && digits(thread))
+}
+
+fn window_dates(manifest: &RunManifest) -> Result<Vec<NaiveDate>> {
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| anyhow!("Invalid date {:?} in run {}: {}", date, manifest.run_id, e));
+    let (start, end) = (parse(&manifest.config.window_start)?, parse(&manifest.config.window_end)?);
+    Ok(start.iter_days().take_while(|date| *date <= end).collect())
+}
+
+/// Delete the files of a run and release its partition counter entries. With `dry_run`, only
+/// report what would go.
+pub async fn purge_run(config: &aws_config::SdkConfig, manifest: &RunManifest, dry_run: bool) -> Result<()> {
+    let dates = window_dates(manifest)?;
+    let objects = match manifest.executor {
+        Executor::Batch => Objects::S3(S3Client::new(config)),
+        // Every array of a local run writes to the same OUTPUT_DIR
+        Executor::Local => {
+            let output_dir = manifest
+                .arrays
+                .first()
+                .and_then(|array| array.environment.get("OUTPUT_DIR"))
+                .ok_or_else(|| anyhow!("Run {} has no OUTPUT_DIR recorded", manifest.run_id))?;
+            Objects::Local(PathBuf::from(output_dir))
+        }
+    };
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    println!("🧹 Purging run {} ({} to {}){}", manifest.run_id, manifest.config.window_start, manifest.config.window_end, if dry_run { " [dry run]" } else { "" });
+
+    // Files are named run_<run_id>_job_<n>_thread_<t>.parquet under <table>/<yyyy>/<mm>/<dd>/
+    let locations = table_buckets(&manifest.config.spec);
+    let prefixes: Vec<(String, String)> = dates
+        .iter()
+        .flat_map(|date| {
+            locations.iter().map(move |(table, bucket)| (bucket.clone(), format!("{}/{}/run_{}_", table, date.format("%Y/%m/%d"), manifest.run_id)))
+        })
+        .collect();
+    let listed: Vec<(&String, Vec<(String, u64)>)> = stream::iter(&prefixes)
+        .map(|(bucket, prefix)| {
+            let objects = &objects;
+            async move { Ok::<_, anyhow::Error>((bucket, objects.list(bucket, prefix).await?)) }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+    let mut by_bucket: HashMap<String, Vec<String>> = HashMap::new();
+    let mut total_bytes = 0;
+    for (bucket, found) in listed {
+        for (key, size) in found.into_iter().filter(|(key, _)| is_run_file(key, &manifest.run_id)) {
+            total_bytes += size;
+            by_bucket.entry(bucket.clone()).or_default().push(key);
+        }
+    }
+    for (bucket, keys) in &by_bucket {
+        if !dry_run {
+            objects.delete(bucket, keys).await?;
+        }
+        println!("   {} {} files from {}", verb, keys.len(), bucket);
+    }
+    let total_files: usize = by_bucket.values().map(Vec::len).sum();
+    println!("🗑️  {} {} files ({}) found under {} prefixes", verb, total_files, format_bytes(total_bytes), prefixes.len());
+
+    let dynamodb_client = DynamoDbClient::new(config);
+    reset_counters(&dynamodb_client, &manifest.config.spec.partition_counter_table_name, manifest, &dates, dry_run).await
+}
+
+enum CounterAction {
+    /// Only this run used the date
+    Delete { job_counter: String },
+    /// Other runs used the date too; their partition orders stay as they are
+    Release { claims: Vec<String>, run_jobs: bool },
+    Untouched,
+}
+
+/// Delete the partition counter of every date only this run used, and remove the run's entries
+/// from the rest. Counters other runs added to keep their value, so no partition order repeats.
+async fn reset_counters(client: &DynamoDbClient, table_name: &str, manifest: &RunManifest, dates: &[NaiveDate], dry_run: bool) -> Result<()> {
+    let run_jobs_attribute = format!("run_jobs_{}", manifest.run_id);
+    // Claims are keyed by the child's job ID, <array job id>:<index> with ':' replaced by '_'
+    let claim_prefixes: Vec<String> = manifest.all_arrays().map(|array| format!("{}_", array.job_id.replace(':', "_"))).collect();
+
+    let actions: Vec<CounterAction> = stream::iter(dates)
+        .map(|date| {
+            let (run_jobs_attribute, claim_prefixes) = (&run_jobs_attribute, &claim_prefixes);
+            async move {
+                let partition_date = date.format("%Y-%m-%d").to_string();
+                let response = client
+                    .get_item()
+                    .table_name(table_name)
+                    .key("partition_date", AttributeValue::S(partition_date.clone()))
+                    .consistent_read(true)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Failed to read partition counter {}: {:?}", partition_date, e))?;
+                let Some(item) = response.item() else { return Ok(CounterAction::Untouched) };
+                let number = |name: &str| item.get(name).and_then(|value| value.as_n().ok()).cloned();
+                let claims: Vec<String> = item
+                    .get("active_jobs")
+                    .and_then(|value| value.as_m().ok())
+                    .map(|active_jobs| active_jobs.keys().filter(|job_id| claim_prefixes.iter().any(|prefix| job_id.starts_with(prefix))).cloned().collect())
+                    .unwrap_or_default();
+                let action = match (number(run_jobs_attribute), number("job_counter")) {
+                    (Some(run_jobs), Some(job_counter)) if run_jobs == job_counter => CounterAction::Delete { job_counter },
+                    (run_jobs, _) if run_jobs.is_some() || !claims.is_empty() => CounterAction::Release { claims, run_jobs: run_jobs.is_some() },
+                    _ => CounterAction::Untouched,
+                };
+                if !dry_run {
+                    apply_counter_action(client, table_name, &partition_date, run_jobs_attribute, &action).await?;
+                }
+                Ok::<_, anyhow::Error>(action)
+            }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+
+    let (mut deleted, mut released, mut claims_removed) = (0, 0, 0);
+    for action in actions {
+        match action {
+            CounterAction::Delete { .. } => deleted += 1,
+            CounterAction::Release { claims, .. } => {
+                released += 1;
+                claims_removed += claims.len();
+            }
+            CounterAction::Untouched => {}
+        }
+    }
+    let verb = if dry_run { "Would reset" } else { "Reset" };
+    println!("🔢 {} partition counters in {}: {} dates only this run used deleted, {} shared dates released ({} claims removed, counters kept)", verb, table_name, deleted, released, claims_removed);
+    Ok(())
+}
+
+async fn apply_counter_action(client: &DynamoDbClient, table_name: &str, partition_date: &str, run_jobs_attribute: &str, action: &CounterAction) -> Result<()> {
+    let key = AttributeValue::S(partition_date.to_string());
+    match action {
+        // Conditional, so a run that claimed the date since it was read keeps its counter
+        CounterAction::Delete { job_counter } => {
+            client
+                .delete_item()
+                .table_name(table_name)
+                .key("partition_date", key)
+                .condition_expression("job_counter = :job_counter")
+                .expression_attribute_values(":job_counter", AttributeValue::N(job_counter.clone()))
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to delete partition counter {} (another run may have used it since): {:?}", partition_date, e))?;
+        }
+        CounterAction::Release { claims, run_jobs } => {
+            let mut names: Vec<String> = claims.clone();
+            if *run_jobs {
+                names.push(run_jobs_attribute.to_string());
+            }
+            for chunk in names.chunks(CLAIMS_PER_UPDATE) {
+                let mut update = client.update_item().table_name(table_name).key("partition_date", key.clone());
+                let mut removals = Vec::with_capacity(chunk.len());
+                for (i, name) in chunk.iter().enumerate() {
+                    let placeholder = format!("#n{}", i);
+                    removals.push(if name == run_jobs_attribute { placeholder.clone() } else { format!("active_jobs.{}", placeholder) });
+                    update = update.expression_attribute_names(placeholder, name);
+                }
+                update
+                    .update_expression(format!("REMOVE {}", removals.join(", ")))
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Failed to release partition counter {}: {:?}", partition_date, e))?;
+            }
+        }
+        CounterAction::Untouched => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_run_file_matches_the_whole_file_name() {
+        assert!(is_run_file("authorization/2024/01/29/run_a_job_1_thread_2.parquet", "a"));
+        assert!(is_run_file("run_a_job_1_thread_2.parquet", "a"));
+        // Run a must not take the files of a run whose ID starts with "a_job_5"
+        assert!(!is_run_file("authorization/2024/01/29/run_a_job_5_job_1_thread_2.parquet", "a"));
+        assert!(is_run_file("authorization/2024/01/29/run_a_job_5_job_1_thread_2.parquet", "a_job_5"));
+        assert!(!is_run_file("authorization/2024/01/29/run_nightly-2_job_1_thread_2.parquet", "nightly"));
+        assert!(!is_run_file("authorization/2024/01/29/run_a_job_1_thread_2.parquet.tmp", "a"));
+        assert!(!is_run_file("authorization/2024/01/29/job_1_thread_2.parquet", "a"));
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A Batch job queue and its share of the jobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueSpec {
+    pub name: String,
+    /// Relative share with weighted distribution
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// Most jobs the queue takes with priority distribution; the last queue takes the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_jobs: Option<i32>,
+}
+
+/// `NAME[=WEIGHT]`, e.g. `spot-queue=80`
+impl FromStr for QueueSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (name, weight) = match value.split_once('=') {
+            Some((name, weight)) => (name, weight.trim().parse().map_err(|e| anyhow!("invalid weight in {:?}: {}", value, e))?),
+            None => (value, default_weight()),
+        };
+        Ok(Self { name: name.trim().to_string(), weight, max_jobs: None })
+    }
+}
+
+/// How a brand's jobs are shared between the queues
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueDistribution {
+    /// In proportion to each queue's weight (largest remainder, ties to the queue listed first)
+    #[default]
+    Weighted,
+    /// Fill queues in the order listed, each up to its max_jobs
+    Priority,
+}
+
+impl QueueDistribution {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueueDistribution::Weighted => "weighted",
+            QueueDistribution::Priority => "priority",
+        }
+    }
+
+    /// Jobs each queue takes out of `jobs`. `remaining_capacity` tracks priority caps across the
+    /// brands of a run.
+    pub fn split(&self, jobs: i32, queues: &[QueueSpec], remaining_capacity: &mut [Option<i32>]) -> Vec<i32> {
+        match self {
+            QueueDistribution::Weighted => {
+                let weights: Vec<f64> = queues.iter().map(|queue| queue.weight).collect();
+                split_by_weight(jobs, &weights)
+            }
+            QueueDistribution::Priority => {
+                let mut unassigned = jobs;
+                let last = queues.len() - 1;
+                remaining_capacity
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, capacity)| {
+                        let take = match capacity {
+                            Some(capacity) if i < last => {
+                                let take = unassigned.min(*capacity);
+                                *capacity -= take;
+                                take
+                            }
+                            _ => unassigned,
+                        };
+                        unassigned -= take;
+                        take
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub fn validate(queues: &[QueueSpec]) -> Result<()> {
+    for (i, queue) in queues.iter().enumerate() {
+        if queue.name.is_empty() {
+            bail!("Invalid run spec field queues[{}].name: must not be empty", i);
+        }
+        if queues[..i].iter().any(|other| other.name == queue.name) {
+            bail!("Invalid run spec field queues[{}]: {} is listed twice", i, queue.name);
+        }
+        if !queue.weight.is_finite() || queue.weight < 0.0 {
+            bail!("Invalid run spec field queues[{}].weight: must be a non-negative number", i);
+        }
+        if queue.max_jobs.is_some_and(|max_jobs| max_jobs < 0) {
+            bail!("Invalid run spec field queues[{}].max_jobs: must not be negative", i);
+        }
+    }
+    if queues.iter().map(|queue| queue.weight).sum::<f64>() <= 0.0 {
+        bail!("Invalid run spec field queues: at least one queue needs a positive weight");
+    }
+    Ok(())
+}
+
+/// Split `total` in proportion to `weights` by largest remainder; ties go to the earlier entry
+pub fn split_by_weight(total: i32, weights: &[f64]) -> Vec<i32> {
+    let total_weight: f64 = weights.iter().sum();
+    let quotas: Vec<f64> = weights.iter().map(|weight| total as f64 * weight / total_weight).collect();
+    let mut counts: Vec<i32> = quotas.iter().map(|quota| quota.floor() as i32).collect();
+    let mut remainders: Vec<(f64, usize)> = quotas.iter().enumerate().map(|(i, quota)| (quota - quota.floor(), i)).collect();
+    remainders.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+    let unassigned = total - counts.iter().sum::<i32>();
+    for (_, i) in remainders.iter().take(unassigned.max(0) as usize) {
+        counts[*i] += 1;
+    }
+    counts
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use aws_sdk_batch::Client as BatchClient;
+use futures::stream::{self, StreamExt};
+
+use crate::manifest::{RetryAttempt, RetrySource, RunManifest, SubmittedArray};
+use crate::{submission, watch, CONCURRENT_SUBMISSIONS};
+
+/// A contiguous range of failed job indexes from one submitted array
+struct RetryGroup<'a> {
+    array: &'a SubmittedArray,
+    first_job_index: i32,
+    size: i32,
+}
+
+/// Resubmit only the failed job indexes of a run's latest attempt. Failures come from Batch, plus
+/// jobs the latest retry could not resubmit, unless `job_indexes` lists them, e.g. once Batch has
+/// expired the original jobs. Returns the new attempt, or None for a dry run or when nothing
+/// failed. Groups Batch does not accept are recorded in the attempt rather than dropped.
+pub async fn retry_failed(
+    client: &BatchClient,
+    manifest: &RunManifest,
+    job_indexes: &[i32],
+    queue: Option<&str>,
+    dry_run: bool,
+) -> Result<Option<RetryAttempt>> {
+    let mut failed: Vec<(i32, &SubmittedArray)> = if job_indexes.is_empty() {
+        let mut failed: Vec<(i32, &SubmittedArray)> = watch::list_failed_children(client, manifest.latest_arrays())
+            .await?
+            .into_iter()
+            .map(|(job_index, array, _)| (job_index, array))
+            .collect();
+        for job_index in manifest.latest_unsubmitted() {
+            failed.push((*job_index, manifest.original_array(*job_index)?));
+        }
+        failed
+    } else {
+        job_indexes
+            .iter()
+            .map(|job_index| Ok((*job_index, manifest.original_array(*job_index)?)))
+            .collect::<Result<_>>()?
+    };
+    failed.sort_by_key(|(job_index, _)| *job_index);
+    failed.dedup_by_key(|(job_index, _)| *job_index);
+    if failed.is_empty() {
+        println!("🎉 No failed jobs to retry");
+        return Ok(None);
+    }
+
+    // Neighbouring failures from the same array go back as one array; the rest as single jobs
+    let mut groups: Vec<RetryGroup> = Vec::new();
+    for (job_index, array) in failed {
+        match groups.last_mut() {
+            Some(group) if std::ptr::eq(group.array, array) && group.first_job_index + group.size == job_index => group.size += 1,
+            _ => groups.push(RetryGroup { array, first_job_index: job_index, size: 1 }),
+        }
+    }
+
+    let total_jobs: i32 = groups.iter().map(|group| group.size).sum();
+    let attempt = manifest.retries.len() + 1;
+    println!("🔁 Retry {} of run {}: {} failed jobs in {} submissions", attempt, manifest.run_id, total_jobs, groups.len());
+    for group in &groups {
+        println!("   jobs {}-{} from batch {} ({}) to {}",
+                 group.first_job_index,
+                 group.first_job_index + group.size - 1,
+                 group.array.batch_num + 1,
+                 group.array.job_id,
+                 queue.unwrap_or(&group.array.queue));
+    }
+    if dry_run {
+        return Ok(None);
+    }
+
+    let mut submission_stream = stream::iter(groups.into_iter().enumerate())
+        .map(|(retry_num, group)| {
+            let client = client.clone();
+            let run_id = manifest.run_id.clone();
+            let job_definition = manifest.job_definition.clone();
+            let queue_name = queue.unwrap_or(&group.array.queue).to_string();
+            let fallback_queue = manifest.config.spec.fallback_queue.clone();
+            let (array, first_job_index, size) = (group.array.clone(), group.first_job_index, group.size);
+
+            let submission = tokio::spawn(async move {
+                if array.environment.is_empty() {
+                    bail!("Batch {} has no recorded environment; the manifest predates retry-failed", array.batch_num + 1);
+                }
+                // Retries of retries still point at the array that first claimed the partition orders
+                let retry_of = array.retry_of.clone().unwrap_or(RetrySource {
+                    array_job_id: array.job_id.clone(),
+                    job_index_offset: array.job_index_offset,
+                    array_child: array.size > 1,
+                });
+                let mut environment = array.environment.clone();
+                environment.insert("JOB_INDEX_OFFSET".to_string(), first_job_index.to_string());
+                environment.insert("RETRY_OF_ARRAY_JOB_ID".to_string(), retry_of.array_job_id.clone());
+                environment.insert("RETRY_OF_JOB_INDEX_OFFSET".to_string(), retry_of.job_index_offset.to_string());
+                environment.insert("RETRY_OF_ARRAY_CHILD".to_string(), retry_of.array_child.to_string());
+
+                let job_name = format!("6table-multiple-buckets-{}-retry{}-{}", run_id, attempt, retry_num + 1);
+                let container = submission::JobContainer { job_definition: &job_definition, command: &[], environment: &environment, depends_on: &[] };
+                let target = submission::QueueTarget { queue: &queue_name, fallback_queue: fallback_queue.as_deref() };
+                let (job_id, queue_name) = submission::submit_with_fallback(&client, &run_id, &job_name, target, &container, size).await?;
+                println!("✅ Retry {} submitted: {} (jobs {}-{}) to {}", retry_num + 1, job_id, first_job_index, first_job_index + size - 1, queue_name);
+                Ok(SubmittedArray {
+                    batch_num: retry_num,
+                    job_id,
+                    job_name,
+                    queue: queue_name,
+                    size,
+                    job_index_offset: first_job_index,
+                    environment,
+                    retry_of: Some(retry_of),
+                })
+            });
+            async move { (first_job_index..first_job_index + size, submission.await) }
+        })
+        .buffer_unordered(CONCURRENT_SUBMISSIONS);
+
+    let mut arrays = Vec::new();
+    let mut unsubmitted_job_indexes = Vec::new();
+    let mut failures = 0;
+    while let Some((job_range, result)) = submission_stream.next().await {
+        match result {
+            Ok(Ok(array)) => arrays.push(array),
+            Ok(Err(e)) => {
+                eprintln!("❌ Retry submission failed for jobs {}-{}: {:?}", job_range.start, job_range.end - 1, e);
+                unsubmitted_job_indexes.extend(job_range);
+                failures += 1;
+            }
+            Err(e) => {
+                eprintln!("❌ Task join failed for jobs {}-{}: {:?}", job_range.start, job_range.end - 1, e);
+                unsubmitted_job_indexes.extend(job_range);
+                failures += 1;
+            }
+        }
+    }
+    // With nothing accepted the previous attempt stays the latest and can simply be retried again
+    if arrays.is_empty() {
+        bail!("All {} retry submissions failed", failures);
+    }
+
+    arrays.sort_by_key(|array| array.batch_num);
+    unsubmitted_job_indexes.sort_unstable();
+    Ok(Some(RetryAttempt {
+        submitted_at: chrono::Utc::now().to_rfc3339(),
+        arrays,
+        unsubmitted_job_indexes,
+    }))
+}
@@ -0,0 +1,391 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::brand_mix::BrandShare;
+use crate::plan::TableCounts;
+use crate::queues::{self, QueueDistribution, QueueSpec};
+use crate::steps::Steps;
+
+/// Options for one output table
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableSpec {
+    /// Generate and upload the table at all
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Also write the table to its specialized bucket, not only the combined one
+    #[serde(default = "default_true")]
+    pub specialized_bucket: bool,
+}
+
+impl Default for TableSpec {
+    fn default() -> Self {
+        Self { enabled: true, specialized_bucket: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableSpecs {
+    #[serde(default)]
+    pub authorization: TableSpec,
+    #[serde(default)]
+    pub authorization_hash: TableSpec,
+    #[serde(default)]
+    pub clearing: TableSpec,
+    #[serde(default)]
+    pub clearing_hash: TableSpec,
+    #[serde(default)]
+    pub chargeback: TableSpec,
+    #[serde(default)]
+    pub chargeback_hash: TableSpec,
+}
+
+impl TableSpecs {
+    fn select(&self, counts: &TableCounts, keep: impl Fn(&TableSpec) -> bool) -> TableCounts {
+        let pick = |spec: &TableSpec, count: u64| if keep(spec) { count } else { 0 };
+        TableCounts {
+            authorization: pick(&self.authorization, counts.authorization),
+            authorization_hash: pick(&self.authorization_hash, counts.authorization_hash),
+            clearing: pick(&self.clearing, counts.clearing),
+            clearing_hash: pick(&self.clearing_hash, counts.clearing_hash),
+            chargeback: pick(&self.chargeback, counts.chargeback),
+            chargeback_hash: pick(&self.chargeback_hash, counts.chargeback_hash),
+        }
+    }
+
+    /// Counts with disabled tables zeroed
+    pub fn enabled(&self, counts: &TableCounts) -> TableCounts {
+        self.select(counts, |spec| spec.enabled)
+    }
+
+    /// Counts of the tables that are also written to a specialized bucket
+    pub fn specialized(&self, counts: &TableCounts) -> TableCounts {
+        self.select(counts, |spec| spec.enabled && spec.specialized_bucket)
+    }
+
+    /// Files one generator thread uploads: every enabled table plus its specialized copy
+    pub fn uploads_per_thread(&self) -> u64 {
+        [self.authorization, self.authorization_hash, self.clearing, self.clearing_hash, self.chargeback, self.chargeback_hash]
+            .iter()
+            .map(|spec| spec.enabled as u64 * (1 + spec.specialized_bucket as u64))
+            .sum()
+    }
+
+    fn any_enabled(&self) -> bool {
+        [self.authorization, self.authorization_hash, self.clearing, self.clearing_hash, self.chargeback, self.chargeback_hash]
+            .iter()
+            .any(|spec| spec.enabled)
+    }
+}
+
+/// LOG_FORMAT of the generator jobs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, with run, job, thread and table fields
+    Json,
+}
+
+impl LogFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+/// Everything a run is configured with. Each field comes from the first of: a CLI flag, a
+/// `--set` override, the spec file, the legacy env var (TARGET_TB, INITIAL_LOAD,
+/// CHARGEBACK_PERCENTAGE, AWS_DEFAULT_REGION), the default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunSpec {
+    #[serde(default = "default_target_tb")]
+    pub target_tb: i32,
+    #[serde(default = "default_true")]
+    pub initial_load: bool,
+    /// Percent of transactions that get a chargeback (0.1 = 0.1%)
+    #[serde(default = "default_chargeback_percentage")]
+    pub chargeback_percentage: f64,
+    /// Region the generator jobs use (AWS_DEFAULT_REGION in the job)
+    #[serde(default = "default_aws_region")]
+    pub aws_region: String,
+    /// NUM_THREADS of each generator job
+    #[serde(default = "default_threads_per_job")]
+    pub threads_per_job: i32,
+    /// NUM_OF_ROWS of each generator thread
+    #[serde(default = "default_rows_per_thread")]
+    pub rows_per_thread: i32,
+    /// vCPUs and memory of the generator job definition, for the cost estimate and local parallelism
+    #[serde(default = "default_vcpus_per_job")]
+    pub vcpus_per_job: i32,
+    #[serde(default = "default_memory_per_job_gb")]
+    pub memory_per_job_gb: i32,
+    #[serde(default = "default_max_array_size")]
+    pub max_array_size: i32,
+    /// The default queue pair, used with equal weights unless `queues` is set
+    #[serde(default)]
+    pub spot_queue_name: Option<String>,
+    #[serde(default)]
+    pub ondemand_queue_name: Option<String>,
+    /// Queues jobs are shared between; replaces spot_queue_name and ondemand_queue_name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub queues: Vec<QueueSpec>,
+    #[serde(default)]
+    pub queue_distribution: QueueDistribution,
+    /// Queue an array is resubmitted to when its submission fails, e.g. on-demand for spot
+    #[serde(default)]
+    pub fallback_queue: Option<String>,
+    /// Required for Batch runs; `--local` runs need neither it nor queues
+    #[serde(default)]
+    pub job_definition: Option<String>,
+    pub payment_data_bucket_name: String,
+    pub clearing_bucket_name: String,
+    pub authorization_bucket_name: String,
+    pub chargeback_bucket_name: String,
+    pub hash_pan_table_name: String,
+    pub partition_counter_table_name: String,
+    /// Brand of every job unless `brand_mix` is set
+    #[serde(default)]
+    pub card_brand: Option<String>,
+    #[serde(default)]
+    pub network_brand: Option<String>,
+    /// Jobs split between several brands by weight; replaces card_brand and network_brand
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub brand_mix: Vec<BrandShare>,
+    #[serde(default)]
+    pub start_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub jobs_per_day: Option<i32>,
+    #[serde(default)]
+    pub date_profile: Option<String>,
+    #[serde(default)]
+    pub calibration: Option<String>,
+    /// Price table for the cost estimate; the bundled prices.json when unset
+    #[serde(default)]
+    pub prices: Option<String>,
+    /// Refuse to submit when the estimated cost is higher
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+    /// Have generator jobs also log their metrics in CloudWatch embedded metric format
+    #[serde(default)]
+    pub metrics_emf: bool,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub tables: TableSpecs,
+    /// Jobs chained before and after the generator arrays
+    #[serde(default)]
+    pub steps: Steps,
+}
+
+impl RunSpec {
+    /// Build the spec from the env, the optional spec file (YAML, or JSON for `.json`), `key=value`
+    /// overrides with dotted keys such as `tables.clearing_hash.enabled=false`, and the CLI flags
+    pub fn load(path: Option<&str>, overrides: &[String], cli: Value) -> Result<Self> {
+        let mut spec = legacy_env()?;
+        if let Some(path) = path {
+            let content = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read run spec {}: {}", path, e))?;
+            let file: Value = if path.ends_with(".json") {
+                serde_json::from_str(&content).map_err(|e| anyhow!("Invalid run spec {}: {}", path, e))?
+            } else {
+                serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid run spec {}: {}", path, e))?
+            };
+            match file {
+                Value::Object(_) => merge(&mut spec, file),
+                Value::Null => {}
+                _ => bail!("Invalid run spec {}: expected a mapping of settings", path),
+            }
+        }
+        for assignment in overrides {
+            let (key, value) = assignment
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid --set {:?}: expected key=value", assignment))?;
+            // Values are JSON when they parse as JSON (numbers, booleans), strings otherwise
+            let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+            let overlay = key.rsplit('.').fold(value, |value, part| Value::Object(Map::from_iter([(part.to_string(), value)])));
+            merge(&mut spec, overlay);
+        }
+        merge(&mut spec, cli);
+
+        let spec: RunSpec = serde_path_to_error::deserialize(spec).map_err(|e| {
+            let field = e.path().to_string();
+            if field == "." {
+                anyhow!("Invalid run spec: {} (set it in --spec, with --set or with its --flag)", e.inner())
+            } else {
+                anyhow!("Invalid run spec field {}: {}", field, e.inner())
+            }
+        })?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Check the settings only Batch submissions need
+    pub fn validate_batch(&self) -> Result<()> {
+        if self.job_definition.is_none() {
+            bail!("Invalid run spec: job_definition is required (set it in --spec, with --set or with its --flag)");
+        }
+        if self.queues.is_empty() && (self.spot_queue_name.is_none() || self.ondemand_queue_name.is_none()) {
+            bail!("Invalid run spec: spot_queue_name and ondemand_queue_name are required unless queues is set");
+        }
+        queues::validate(&self.queues())?;
+        self.steps.validate_batch()
+    }
+
+    /// Check the settings only `--local` runs need
+    pub fn validate_local(&self) -> Result<()> {
+        self.steps.validate_local()
+    }
+
+    /// The queues jobs are shared between
+    pub fn queues(&self) -> Vec<QueueSpec> {
+        if !self.queues.is_empty() {
+            return self.queues.clone();
+        }
+        [&self.spot_queue_name, &self.ondemand_queue_name]
+            .into_iter()
+            .map(|name| QueueSpec { name: name.clone().unwrap_or_default(), weight: 1.0, max_jobs: None })
+            .collect()
+    }
+
+    /// The brands jobs are split between; a single-brand run is a mix of one
+    pub fn brands(&self) -> Vec<BrandShare> {
+        if !self.brand_mix.is_empty() {
+            return self.brand_mix.clone();
+        }
+        vec![BrandShare {
+            card_brand: self.card_brand.clone().unwrap_or_default(),
+            network_brand: self.network_brand.clone(),
+            weight: 1.0,
+        }]
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.target_tb < 1 {
+            bail!("Invalid run spec field target_tb: must be at least 1");
+        }
+        if !(0.0..=100.0).contains(&self.chargeback_percentage) {
+            bail!("Invalid run spec field chargeback_percentage: must be between 0 and 100");
+        }
+        // The generator seeds each thread with job_index * 1000 + thread_id
+        if !(1..=999).contains(&self.threads_per_job) {
+            bail!("Invalid run spec field threads_per_job: must be between 1 and 999");
+        }
+        if self.rows_per_thread < 1 {
+            bail!("Invalid run spec field rows_per_thread: must be at least 1");
+        }
+        if self.vcpus_per_job < 1 || self.memory_per_job_gb < 1 {
+            bail!("Invalid run spec: vcpus_per_job and memory_per_job_gb must be at least 1");
+        }
+        // Batch array jobs have 2 to 10,000 children
+        if !(2..=10000).contains(&self.max_array_size) {
+            bail!("Invalid run spec field max_array_size: must be between 2 and 10000");
+        }
+        match (self.start_date, self.end_date) {
+            (Some(start_date), Some(end_date)) if end_date < start_date => {
+                bail!("Invalid run spec: end_date {} is before start_date {}", end_date, start_date)
+            }
+            (Some(_), None) | (None, Some(_)) => bail!("Invalid run spec: start_date and end_date must be set together"),
+            (None, None) if self.jobs_per_day.is_some() => bail!("Invalid run spec: jobs_per_day needs start_date and end_date"),
+            _ => {}
+        }
+        if self.jobs_per_day.is_some_and(|jobs_per_day| jobs_per_day < 1) {
+            bail!("Invalid run spec field jobs_per_day: must be at least 1");
+        }
+        if self.brand_mix.is_empty() && (self.card_brand.is_none() || self.network_brand.is_none()) {
+            bail!("Invalid run spec: card_brand and network_brand are required unless brand_mix is set");
+        }
+        for (i, brand) in self.brand_mix.iter().enumerate() {
+            if !brand.weight.is_finite() || brand.weight <= 0.0 {
+                bail!("Invalid run spec field brand_mix[{}].weight: must be a positive number", i);
+            }
+            if self.brand_mix[..i].iter().any(|other| other.label() == brand.label()) {
+                bail!("Invalid run spec field brand_mix[{}]: {} is listed twice", i, brand.label());
+            }
+        }
+        if self.max_cost.is_some_and(|max_cost| !max_cost.is_finite() || max_cost <= 0.0) {
+            bail!("Invalid run spec field max_cost: must be a positive number");
+        }
+        if !self.tables.any_enabled() {
+            bail!("Invalid run spec field tables: at least one table must be enabled");
+        }
+        Ok(())
+    }
+}
+
+/// The env vars job-submitter read before run specs existed
+fn legacy_env() -> Result<Value> {
+    let mut spec = Map::new();
+    if let Ok(value) = std::env::var("TARGET_TB") {
+        let target_tb: i32 = value.parse().map_err(|e| anyhow!("Invalid TARGET_TB {:?}: {}", value, e))?;
+        spec.insert("target_tb".to_string(), target_tb.into());
+    }
+    if let Ok(value) = std::env::var("INITIAL_LOAD") {
+        spec.insert("initial_load".to_string(), (value.to_lowercase() == "true").into());
+    }
+    if let Ok(value) = std::env::var("CHARGEBACK_PERCENTAGE") {
+        let chargeback_percentage: f64 = value.parse().map_err(|e| anyhow!("Invalid CHARGEBACK_PERCENTAGE {:?}: {}", value, e))?;
+        spec.insert("chargeback_percentage".to_string(), chargeback_percentage.into());
+    }
+    if let Ok(value) = std::env::var("AWS_DEFAULT_REGION") {
+        spec.insert("aws_region".to_string(), value.into());
+    }
+    Ok(Value::Object(spec))
+}
+
+/// Overlay `overlay` onto `base`, merging nested mappings key by key
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_target_tb() -> i32 {
+    500
+}
+
+fn default_chargeback_percentage() -> f64 {
+    0.1
+}
+
+fn default_aws_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_threads_per_job() -> i32 {
+    3
+}
+
+fn default_rows_per_thread() -> i32 {
+    1000000
+}
+
+// The CDK job definition's container size
+fn default_vcpus_per_job() -> i32 {
+    4
+}
+
+fn default_memory_per_job_gb() -> i32 {
+    28
+}
+
+fn default_max_array_size() -> i32 {
+    1000
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Result};
+use aws_sdk_batch::Client as BatchClient;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::manifest::SubmittedStep;
+use crate::submission::{self, JobContainer};
+
+// SubmitJob accepts at most 20 dependencies per job
+const MAX_DEPENDENCIES: usize = 20;
+
+/// A single job chained before or after the generator arrays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepSpec {
+    /// Batch job definition; required for Batch runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_definition: Option<String>,
+    /// Defaults to the first queue of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<String>,
+    /// Overrides the image's command, e.g. for an image holding several binaries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// Program a `--local` run starts instead, with `args`; required for local runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Added to the environment every generator job gets (bucket and table names, RUN_ID, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+}
+
+impl StepSpec {
+    pub fn job_environment(&self, base_environment: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut environment = base_environment.clone();
+        environment.extend(self.environment.clone());
+        environment
+    }
+}
+
+/// The run's dependency graph: seed → generator arrays → validate
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Steps {
+    /// Must succeed before any generator job starts, e.g. the DynamoDB seeder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<StepSpec>,
+    /// Starts once every generator job has succeeded, e.g. validation or compaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate: Option<StepSpec>,
+}
+
+impl Steps {
+    fn iter(&self) -> impl Iterator<Item = (&'static str, &StepSpec)> {
+        [("seed", &self.seed), ("validate", &self.validate)]
+            .into_iter()
+            .filter_map(|(name, step)| step.as_ref().map(|step| (name, step)))
+    }
+
+    pub fn validate_batch(&self) -> Result<()> {
+        if let Some((name, _)) = self.iter().find(|(_, step)| step.job_definition.is_none()) {
+            bail!("Invalid run spec field steps.{}.job_definition: required for Batch runs", name);
+        }
+        Ok(())
+    }
+
+    pub fn validate_local(&self) -> Result<()> {
+        if let Some((name, _)) = self.iter().find(|(_, step)| step.binary.is_none()) {
+            bail!("Invalid run spec field steps.{}.binary: required with --local", name);
+        }
+        Ok(())
+    }
+
+    /// e.g. `seed → 3 arrays → validate`, or None without steps
+    pub fn describe(&self, arrays: usize) -> Option<String> {
+        if self.seed.is_none() && self.validate.is_none() {
+            return None;
+        }
+        let mut graph = Vec::new();
+        graph.extend(self.seed.as_ref().map(|_| "seed".to_string()));
+        graph.push(format!("{} arrays", arrays));
+        graph.extend(self.validate.as_ref().map(|_| "validate".to_string()));
+        Some(graph.join(" → "))
+    }
+}
+
+/// Submits the steps of one Batch run
+pub struct BatchSteps<'a> {
+    pub client: &'a BatchClient,
+    pub run_id: &'a str,
+    pub default_queue: &'a str,
+    /// The generator's job definition, which barrier jobs run with GENERATOR_MODE=barrier
+    pub barrier_job_definition: &'a str,
+    pub base_environment: &'a BTreeMap<String, String>,
+}
+
+impl BatchSteps<'_> {
+    /// Submit `step` to wait for `depends_on`. Beyond 20 dependencies, barrier jobs of up to 20
+    /// each join them first. Returns the barriers, then the step.
+    pub async fn submit(&self, name: &str, step: &StepSpec, mut depends_on: Vec<String>) -> Result<Vec<SubmittedStep>> {
+        let mut submitted = Vec::new();
+        let barrier_environment = BTreeMap::from([
+            ("GENERATOR_MODE".to_string(), "barrier".to_string()),
+            ("RUN_ID".to_string(), self.run_id.to_string()),
+        ]);
+        while depends_on.len() > MAX_DEPENDENCIES {
+            let mut joined = Vec::new();
+            for chunk in depends_on.chunks(MAX_DEPENDENCIES) {
+                let barrier_name = format!("{}-barrier-{}", name, submitted.len() + 1);
+                let container = JobContainer {
+                    job_definition: self.barrier_job_definition,
+                    command: &[],
+                    environment: &barrier_environment,
+                    depends_on: chunk,
+                };
+                let barrier = self.submit_job(&barrier_name, self.default_queue, &container).await?;
+                joined.push(barrier.job_id.clone());
+                submitted.push(barrier);
+            }
+            depends_on = joined;
+        }
+
+        let environment = step.job_environment(self.base_environment);
+        let container = JobContainer {
+            job_definition: step.job_definition.as_deref().unwrap_or_default(),
+            command: &step.command,
+            environment: &environment,
+            depends_on: &depends_on,
+        };
+        let queue = step.queue.as_deref().unwrap_or(self.default_queue);
+        submitted.push(self.submit_job(name, queue, &container).await?);
+        Ok(submitted)
+    }
+
+    async fn submit_job(&self, name: &str, queue: &str, container: &JobContainer<'_>) -> Result<SubmittedStep> {
+        let job_name = format!("6table-multiple-buckets-{}-{}", self.run_id, name);
+        let job_id = submission::submit_array(self.client, self.run_id, &job_name, queue, container, 1).await?;
+        println!("🔗 Step {} submitted: {} to {} (waits for {} jobs)", name, job_id, queue, container.depends_on.len());
+        Ok(SubmittedStep {
+            name: name.to_string(),
+            job_id,
+            job_name,
+            queue: queue.to_string(),
+            depends_on: container.depends_on.to_vec(),
+            exit_code: None,
+        })
+    }
+}
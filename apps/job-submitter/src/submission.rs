@@ -0,0 +1,87 @@
+use anyhow::{anyhow, Result};
+use aws_sdk_batch::types::{ArrayProperties, ContainerOverrides, JobDependency, KeyValuePair};
+use aws_sdk_batch::Client as BatchClient;
+use std::collections::BTreeMap;
+
+/// What a submitted job runs and what it waits for
+pub struct JobContainer<'a> {
+    pub job_definition: &'a str,
+    /// Overrides the image's command when not empty
+    pub command: &'a [String],
+    pub environment: &'a BTreeMap<String, String>,
+    /// Job IDs that must succeed before this job starts; Batch takes at most 20
+    pub depends_on: &'a [String],
+}
+
+/// Submit `size` children of `container`, tagged with the run ID, and return the job ID. Batch
+/// arrays need at least two children, so a single job is submitted without array properties.
+pub async fn submit_array(
+    client: &BatchClient,
+    run_id: &str,
+    job_name: &str,
+    queue: &str,
+    container: &JobContainer<'_>,
+    size: i32,
+) -> Result<String> {
+    let mut container_overrides = ContainerOverrides::builder();
+    for (name, value) in container.environment {
+        container_overrides = container_overrides
+            .environment(KeyValuePair::builder()
+                .name(name)
+                .value(value)
+                .build());
+    }
+    for part in container.command {
+        container_overrides = container_overrides.command(part);
+    }
+
+    let mut request = client
+        .submit_job()
+        .job_name(job_name)
+        .job_queue(queue)
+        .job_definition(container.job_definition)
+        .container_overrides(container_overrides.build())
+        .tags("RunId", run_id)
+        .propagate_tags(true);
+    if size > 1 {
+        request = request.array_properties(ArrayProperties::builder().size(size).build());
+    }
+    for job_id in container.depends_on {
+        request = request.depends_on(JobDependency::builder().job_id(job_id).build());
+    }
+
+    let response = request.send().await.map_err(|e| anyhow!("SubmitJob {} failed: {:?}", job_name, e))?;
+    response
+        .job_id()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("SubmitJob {} returned no job ID", job_name))
+}
+
+/// Where an array goes: its planned queue, and the queue to try when that submission fails
+pub struct QueueTarget<'a> {
+    pub queue: &'a str,
+    pub fallback_queue: Option<&'a str>,
+}
+
+/// Submit to the target queue, falling back to its fallback queue on error. Returns the job ID
+/// and the queue that accepted the array.
+pub async fn submit_with_fallback(
+    client: &BatchClient,
+    run_id: &str,
+    job_name: &str,
+    target: QueueTarget<'_>,
+    container: &JobContainer<'_>,
+    size: i32,
+) -> Result<(String, String)> {
+    match submit_array(client, run_id, job_name, target.queue, container, size).await {
+        Ok(job_id) => Ok((job_id, target.queue.to_string())),
+        Err(e) => match target.fallback_queue {
+            Some(fallback_queue) if fallback_queue != target.queue => {
+                eprintln!("⚠️ {} was not accepted by {}, falling back to {}: {:?}", job_name, target.queue, fallback_queue, e);
+                let job_id = submit_array(client, run_id, job_name, fallback_queue, container, size).await?;
+                Ok((job_id, fallback_queue.to_string()))
+            }
+            _ => Err(e),
+        },
+    }
+}
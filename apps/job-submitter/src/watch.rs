@@ -0,0 +1,237 @@
+use anyhow::{anyhow, bail, Result};
+use aws_sdk_batch::types::JobStatus;
+use aws_sdk_batch::Client as BatchClient;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::ledger;
+use crate::manifest::{RunManifest, RunStatus, SubmittedArray, SubmittedStep};
+
+// DescribeJobs accepts at most 100 job IDs per call
+const DESCRIBE_CHUNK_SIZE: usize = 100;
+const STATUSES: [&str; 7] = ["SUBMITTED", "PENDING", "RUNNABLE", "STARTING", "RUNNING", "SUCCEEDED", "FAILED"];
+const MAX_LISTED_FAILURES: usize = 20;
+
+struct ArrayProgress {
+    size: i32,
+    finished: bool,
+    counts: BTreeMap<String, i32>,
+}
+
+/// Watch the latest attempt of a run, record its final status, and error if any job failed
+pub async fn watch_run(client: &BatchClient, manifest: &mut RunManifest, manifest_path: Option<&str>, interval: Duration) -> Result<()> {
+    println!("🏷️  Run {}", manifest.run_id);
+    let arrays = manifest.latest_arrays().to_vec();
+    let (mut failed_jobs, mut total_jobs) = watch_arrays(client, &arrays, interval).await?;
+    let unsubmitted = manifest.latest_unsubmitted();
+    if !unsubmitted.is_empty() {
+        eprintln!("❌ {} jobs were never resubmitted by the latest retry: {:?}", unsubmitted.len(), unsubmitted);
+        failed_jobs += unsubmitted.len() as i32;
+        total_jobs += unsubmitted.len() as i32;
+    }
+    // Steps only wait for the first attempt's arrays; retries have none
+    let failed_steps = if manifest.retries.is_empty() && !manifest.steps.is_empty() {
+        watch_steps(client, &manifest.steps, interval).await?
+    } else {
+        Vec::new()
+    };
+
+    manifest.status = if failed_jobs == 0 && failed_steps.is_empty() { RunStatus::Succeeded } else { RunStatus::Failed };
+    manifest.updated_at = chrono::Utc::now().to_rfc3339();
+    ledger::record(manifest, manifest_path).await?;
+
+    if failed_jobs > 0 {
+        return Err(anyhow!("{} of {} jobs in run {} failed", failed_jobs, total_jobs, manifest.run_id));
+    }
+    if !failed_steps.is_empty() {
+        return Err(anyhow!("Steps {} of run {} failed", failed_steps.join(", "), manifest.run_id));
+    }
+    Ok(())
+}
+
+/// Poll the arrays until every child has finished, printing status counts and an ETA. Returns the
+/// number of children that ended FAILED, i.e. after Batch exhausted their retries, and the total.
+async fn watch_arrays(client: &BatchClient, arrays: &[SubmittedArray], interval: Duration) -> Result<(i32, i32)> {
+    if arrays.is_empty() {
+        bail!("No array jobs to watch");
+    }
+    let planned_jobs: i32 = arrays.iter().map(|array| array.size).sum();
+    println!("👀 Watching {} array jobs ({} jobs), polling every {}s", arrays.len(), planned_jobs, interval.as_secs());
+
+    // ETA is the completion rate since the first poll, so jobs finished before watching started don't skew it
+    let mut baseline: Option<(Instant, i32)> = None;
+    let (counts, total_jobs) = loop {
+        let progress = describe_arrays(client, arrays).await?;
+        let mut counts: BTreeMap<&str, i32> = STATUSES.iter().map(|status| (*status, 0)).collect();
+        for array in &progress {
+            for (status, count) in &array.counts {
+                if let Some(total) = counts.get_mut(status.as_str()) {
+                    *total += count;
+                }
+            }
+        }
+        let total_jobs: i32 = progress.iter().map(|array| array.size).sum();
+        let finished = counts["SUCCEEDED"] + counts["FAILED"];
+        let (baseline_at, baseline_finished) = *baseline.get_or_insert((Instant::now(), finished));
+        let eta = if finished > baseline_finished && finished < total_jobs {
+            let seconds_per_job = baseline_at.elapsed().as_secs_f64() / (finished - baseline_finished) as f64;
+            format_duration((seconds_per_job * (total_jobs - finished) as f64) as u64)
+        } else {
+            "unknown".to_string()
+        };
+        let status_line: Vec<String> = counts
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(status, count)| format!("{} {}", status, count))
+            .collect();
+        println!(
+            "⏱️  [{}] {}/{} finished ({:.1}%) | {} | ETA {}",
+            chrono::Utc::now().format("%H:%M:%S"),
+            finished,
+            total_jobs,
+            finished as f64 * 100.0 / total_jobs.max(1) as f64,
+            status_line.join(" "),
+            eta
+        );
+
+        if progress.iter().all(|array| array.finished) {
+            break (counts, total_jobs);
+        }
+        tokio::time::sleep(interval).await;
+    };
+
+    if counts["FAILED"] == 0 {
+        println!("🎉 All {} jobs succeeded", total_jobs);
+        return Ok((0, total_jobs));
+    }
+
+    let failed_job_indexes = list_failed_children(client, arrays).await?;
+    for (job_index, array, reason) in failed_job_indexes.iter().take(MAX_LISTED_FAILURES) {
+        eprintln!("❌ Job index {} (batch {}, {}): {}", job_index, array.batch_num + 1, array.job_id, reason);
+    }
+    if failed_job_indexes.len() > MAX_LISTED_FAILURES {
+        eprintln!("❌ ... and {} more", failed_job_indexes.len() - MAX_LISTED_FAILURES);
+    }
+    Ok((counts["FAILED"], total_jobs))
+}
+
+/// Poll the seed, barrier and validate jobs until each has finished. Returns the failed ones.
+async fn watch_steps(client: &BatchClient, steps: &[SubmittedStep], interval: Duration) -> Result<Vec<String>> {
+    loop {
+        let mut statuses = Vec::with_capacity(steps.len());
+        for chunk in steps.chunks(DESCRIBE_CHUNK_SIZE) {
+            let response = client
+                .describe_jobs()
+                .set_jobs(Some(chunk.iter().map(|step| step.job_id.clone()).collect()))
+                .send()
+                .await
+                .map_err(|e| anyhow!("DescribeJobs failed: {:?}", e))?;
+            for step in chunk {
+                let job = response
+                    .jobs()
+                    .iter()
+                    .find(|job| job.job_id() == Some(step.job_id.as_str()))
+                    .ok_or_else(|| anyhow!("Step {} ({}) was not found; Batch keeps finished jobs for about 7 days", step.name, step.job_id))?;
+                let status = job.status().map(|status| status.as_str().to_string()).unwrap_or_default();
+                statuses.push((step, status, job.status_reason().unwrap_or("no reason given").to_string()));
+            }
+        }
+
+        let status_line: Vec<String> = statuses.iter().map(|(step, status, _)| format!("{} {}", step.name, status)).collect();
+        println!("⏱️  [{}] Steps: {}", chrono::Utc::now().format("%H:%M:%S"), status_line.join(", "));
+        if statuses.iter().all(|(_, status, _)| status == "SUCCEEDED" || status == "FAILED") {
+            let mut failed = Vec::new();
+            for (step, _, reason) in statuses.iter().filter(|(_, status, _)| status == "FAILED") {
+                eprintln!("❌ Step {} ({}): {}", step.name, step.job_id, reason);
+                failed.push(step.name.clone());
+            }
+            return Ok(failed);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn describe_arrays(client: &BatchClient, arrays: &[SubmittedArray]) -> Result<Vec<ArrayProgress>> {
+    let mut progress = Vec::with_capacity(arrays.len());
+    for chunk in arrays.chunks(DESCRIBE_CHUNK_SIZE) {
+        let response = client
+            .describe_jobs()
+            .set_jobs(Some(chunk.iter().map(|array| array.job_id.clone()).collect()))
+            .send()
+            .await
+            .map_err(|e| anyhow!("DescribeJobs failed: {:?}", e))?;
+
+        for array in chunk {
+            let job = response
+                .jobs()
+                .iter()
+                .find(|job| job.job_id() == Some(array.job_id.as_str()))
+                .ok_or_else(|| anyhow!("Array job {} ({}) was not found; Batch keeps finished jobs for about 7 days", array.job_id, array.job_name))?;
+            let status = job.status().map(|status| status.as_str().to_string()).unwrap_or_default();
+            let properties = job.array_properties();
+            let size = properties.and_then(|properties| properties.size()).unwrap_or(array.size);
+            // Children appear in the summary as Batch creates them; until then the parent's status stands in
+            let counts = match properties.and_then(|properties| properties.status_summary()) {
+                Some(summary) if !summary.is_empty() => summary.iter().map(|(status, count)| (status.clone(), *count)).collect(),
+                _ => BTreeMap::from([(status.clone(), size)]),
+            };
+            progress.push(ArrayProgress {
+                size,
+                finished: status == "SUCCEEDED" || status == "FAILED",
+                counts,
+            });
+        }
+    }
+    Ok(progress)
+}
+
+/// Failed children as (job index, array, status reason), in job index order
+pub async fn list_failed_children<'a>(client: &BatchClient, arrays: &'a [SubmittedArray]) -> Result<Vec<(i32, &'a SubmittedArray, String)>> {
+    let mut failed = Vec::new();
+    for array in arrays {
+        // A single job has no children to list; its own status is the answer
+        if array.size == 1 {
+            let response = client
+                .describe_jobs()
+                .jobs(&array.job_id)
+                .send()
+                .await
+                .map_err(|e| anyhow!("DescribeJobs failed for {}: {:?}", array.job_id, e))?;
+            if let Some(job) = response.jobs().iter().find(|job| job.status() == Some(&JobStatus::Failed)) {
+                failed.push((array.job_index_offset, array, job.status_reason().unwrap_or("no reason given").to_string()));
+            }
+            continue;
+        }
+
+        let mut next_token = None;
+        loop {
+            let response = client
+                .list_jobs()
+                .array_job_id(&array.job_id)
+                .job_status(JobStatus::Failed)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| anyhow!("ListJobs failed for {}: {:?}", array.job_id, e))?;
+            for child in response.job_summary_list() {
+                if let Some(index) = child.array_properties().and_then(|properties| properties.index()) {
+                    failed.push((array.job_index_offset + index, array, child.status_reason().unwrap_or("no reason given").to_string()));
+                }
+            }
+            next_token = response.next_token().map(str::to_string);
+            if next_token.is_none() {
+                break;
+            }
+        }
+    }
+    failed.sort_by_key(|(job_index, _, _)| *job_index);
+    Ok(failed)
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s >= 3600 => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
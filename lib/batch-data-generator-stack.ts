@@ -12,6 +12,7 @@ import * as s3deploy from 'aws-cdk-lib/aws-s3-deployment';
 import * as cr from 'aws-cdk-lib/custom-resources';
 import * as dynamodb from 'aws-cdk-lib/aws-dynamodb';
 import * as lambda from 'aws-cdk-lib/aws-lambda';
+import * as secretsmanager from 'aws-cdk-lib/aws-secretsmanager';
 import { BlockPublicAccess, BucketEncryption } from 'aws-cdk-lib/aws-s3';
 import * as fs from 'fs';
 import * as path from 'path';
@@ -29,6 +30,8 @@ export class BatchDataGeneratorStack extends cdk.Stack {
     const chargebackPercentage = config.CHARGEBACK_PERCENTAGE;
     const cardBrand = config.CARD_BRAND;
     const networkBrand = config.NETWORK_BRAND;
+    const numOfCards = config.NUM_OF_CARDS ?? 100000;
+    const cardExportPanMode = config.CARD_EXPORT_PAN_MODE ?? 'omit';
 
     // === Part 1: Build Infrastructure ===
 
@@ -146,6 +149,28 @@ export class BatchDataGeneratorStack extends cdk.Stack {
       removalPolicy: cdk.RemovalPolicy.DESTROY,
     });
 
+    // HMAC secret for hash_pan values (rotate by changing HASH_PAN_KEY_ID and running the seeder in rehash mode)
+    const hashPanSecret = new secretsmanager.Secret(this, 'HashPanSecret', {
+      secretName: `${this.account}-hash-pan-secret`,
+      generateSecretString: {
+        passwordLength: 64,
+        excludePunctuation: true,
+      },
+      removalPolicy: cdk.RemovalPolicy.DESTROY,
+    });
+
+    // AES-256 key (64 hex characters) for CARD_EXPORT_PAN_MODE=encrypt
+    const cardExportKeySecret = new secretsmanager.Secret(this, 'CardExportKeySecret', {
+      secretName: `${this.account}-card-export-key`,
+      generateSecretString: {
+        passwordLength: 64,
+        excludePunctuation: true,
+        excludeUppercase: true,
+        excludeCharacters: 'ghijklmnopqrstuvwxyz',
+      },
+      removalPolicy: cdk.RemovalPolicy.DESTROY,
+    });
+
     // DynamoDB table for partition sequence counters
     const partitionCounterTable = new dynamodb.Table(this, 'PartitionCounterTable', {
       tableName: `${this.account}-partition-counter-table`,
@@ -276,19 +301,36 @@ export class BatchDataGeneratorStack extends cdk.Stack {
         AUTHORIZATION_BUCKET_NAME: { value: authorizationBucket.bucketName },
         CHARGEBACK_BUCKET_NAME: { value: chargebackBucket.bucketName },
         HASH_PAN_TABLE_NAME: { value: hashPanTable.tableName },
+        HASH_PAN_SECRET: {
+          value: hashPanSecret.secretArn,
+          type: codebuild.BuildEnvironmentVariableType.SECRETS_MANAGER,
+        },
+        HASH_PAN_KEY_ID: { value: 'v1' },
         PARTITION_COUNTER_TABLE_NAME: { value: partitionCounterTable.tableName },
         TARGET_TB: { value: targetTB.toString() },
         INITIAL_LOAD: { value: initialLoad.toString() },
         CHARGEBACK_PERCENTAGE: { value: chargebackPercentage.toString() },
         CARD_BRAND: { value: cardBrand },
         NETWORK_BRAND: { value: networkBrand },
+        NUM_OF_CARDS: { value: numOfCards.toString() },
+        CARD_EXPORT_PATH: { value: `s3://${paymentDataBucket.bucketName}` },
+        CARD_EXPORT_PAN_MODE: { value: cardExportPanMode },
+        CARD_EXPORT_ENCRYPTION_KEY: {
+          value: cardExportKeySecret.secretArn,
+          type: codebuild.BuildEnvironmentVariableType.SECRETS_MANAGER,
+        },
       },
       buildSpec: codebuild.BuildSpec.fromSourceFilename('buildspec.yml'),
     });
 
     ecrRepo.grantPullPush(buildProject.role!);
     sourceBucket.grantRead(buildProject.role!);
-    hashPanTable.grantWriteData(buildProject.role!);
+    hashPanTable.grantReadWriteData(buildProject.role!);
+    hashPanSecret.grantRead(buildProject.role!);
+    cardExportKeySecret.grantRead(buildProject.role!);
+    // The seeder replaces the card table under the payment data bucket after seeding
+    paymentDataBucket.grantReadWrite(buildProject.role!);
+    paymentDataBucket.grantDelete(buildProject.role!);
     
     // Grant Batch permissions for single queue
     buildProject.addToRolePolicy(new iam.PolicyStatement({
@@ -23,7 +23,7 @@ export class GlueTablesConstruct extends Construct {
       catalogId: cdk.Stack.of(this).account,
       databaseInput: {
         name: 'payment_data',
-        description: '6-table payment processing data: authorization, authorization_hash, clearing, clearing_hash, chargeback, chargeback_hash',
+        description: 'Payment processing data: authorization, authorization_hash, clearing, clearing_hash, chargeback, chargeback_hash and card',
         locationUri: props.paymentDataBucket.s3UrlForObject(),
       },
     });
@@ -274,6 +274,38 @@ export class GlueTablesConstruct extends Construct {
         },
       },
     });
+
+    // Create Card Table (exported by the DynamoDB seeder; pan_* columns are null unless CARD_EXPORT_PAN_MODE writes them)
+    new glue.CfnTable(this, 'CardTable', {
+      catalogId: cdk.Stack.of(this).account,
+      databaseName: this.database.ref,
+      tableInput: {
+        name: 'card',
+        tableType: 'EXTERNAL_TABLE',
+        storageDescriptor: {
+          columns: [
+            { name: 'id', type: 'bigint' },
+            { name: 'hash_pan', type: 'string' },
+            { name: 'hash_key_id', type: 'string' },
+            { name: 'previous_hash_pan', type: 'string' },
+            { name: 'previous_hash_key_id', type: 'string' },
+            { name: 'card_type', type: 'string' },
+            { name: 'bin', type: 'string' },
+            { name: 'issuer', type: 'string' },
+            { name: 'issuer_country', type: 'string' },
+            { name: 'pan_masked', type: 'string' },
+            { name: 'pan_encrypted', type: 'string' },
+            { name: 'pan_encryption_key_id', type: 'string' },
+          ],
+          location: `${props.paymentDataBucket.s3UrlForObject()}/card/`,
+          inputFormat: 'org.apache.hadoop.hive.ql.io.parquet.MapredParquetInputFormat',
+          outputFormat: 'org.apache.hadoop.hive.ql.io.parquet.MapredParquetOutputFormat',
+          serdeInfo: {
+            serializationLibrary: 'org.apache.hadoop.hive.ql.io.parquet.serde.ParquetHiveSerDe',
+          },
+        },
+      },
+    });
   }
 
   private convertSchemaToColumns(schema: any): glue.CfnTable.ColumnProperty[] {